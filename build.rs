@@ -1,4 +1,5 @@
 extern crate bindgen;
+extern crate cc;
 
 use std::env;
 use std::path::PathBuf;
@@ -20,6 +21,13 @@ fn main() {
         .allowlist_type("Chuck_DL_Query")
         .allowlist_type("Chuck_DL_Api::Api")
         .allowlist_type("Chuck_Object")
+        .allowlist_type("Chuck_String")
+        .allowlist_type("Chuck_Array4")
+        .allowlist_type("Chuck_Array8")
+        .allowlist_type("Chuck_Array16")
+        .allowlist_type("Chuck_Array24")
+        .allowlist_type("Chuck_Array32")
+        .allowlist_type("Chuck_Event")
         .opaque_type("Chuck_Carrier")
         .opaque_type("Chuck_Compiler")
         .opaque_type("Chuck_VM")
@@ -28,6 +36,13 @@ fn main() {
         .opaque_type("Chuck_DL_Class")
         .opaque_type("Chuck_DL_Func")
         .opaque_type("Chuck_VTable")
+        // only handled through pointers; contents are reached via the shims
+        .opaque_type("Chuck_Array4")
+        .opaque_type("Chuck_Array8")
+        .opaque_type("Chuck_Array16")
+        .opaque_type("Chuck_Array24")
+        .opaque_type("Chuck_Array32")
+        .opaque_type("Chuck_Event")
         .opaque_type("std::string")
         .opaque_type("std::vector")
         .opaque_type("std::map")
@@ -44,4 +59,18 @@ fn main() {
     bindings
         .write_to_file(out_path.join("bindings.rs"))
         .expect("Couldn't write bindings!");
+
+    // Compile the C++ wrappers for things the bindings can't reach
+    println!("cargo:rerun-if-changed=include/api_shim.cpp");
+    println!("cargo:rerun-if-changed=include/object_shim.cpp");
+    println!("cargo:rerun-if-changed=include/io_shim.cpp");
+    println!("cargo:rerun-if-changed=include/vm_shim.cpp");
+    cc::Build::new()
+        .cpp(true)
+        .include("include")
+        .file("include/api_shim.cpp")
+        .file("include/object_shim.cpp")
+        .file("include/io_shim.cpp")
+        .file("include/vm_shim.cpp")
+        .compile("chugin_shim");
 }
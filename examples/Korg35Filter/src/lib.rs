@@ -1,77 +1,63 @@
 use chugin;
 use chugin::chuck;
+use chugin::param::{ParamInfo, RampTime, Scaling, Smoothed};
 
 use dspz::types::Float;
 use dspz::filter::korg35::Korg35;
 use dspz::traits::Processor;
 
-static mut DATA_OFFSET: usize = 0;
+static DATA_OFFSET: chugin::DataOffset = chugin::DataOffset::new();
 
-chugin::ctor!(ctor, DATA_OFFSET, {
-    let obj = Korg35::new(44100.0);
-    obj
+static FREQ: ParamInfo = ParamInfo::new("freq", 20.0, 20000.0, 1000.0)
+    .unit("Hz")
+    .scaling(Scaling::Log)
+    .below_nyquist();
+
+static K: ParamInfo = ParamInfo::new("K", 0.0, 2.0, 1.0);
+
+struct Korg35Chugin {
+    k35: Korg35,
+    freq: Smoothed<Float>,
+    k: Smoothed<Float>,
+}
+
+chugin::ctor!(ctor, DATA_OFFSET, ctx, {
+    let srate = ctx.srate().unwrap_or(44100) as Float;
+    let mut k35 = Korg35::new(srate);
+    k35.set(FREQ.default, K.default);
+    Korg35Chugin {
+        freq: Smoothed::one_pole(FREQ.default, RampTime::Ms(10.0), srate),
+        k: Smoothed::one_pole(K.default, RampTime::Ms(10.0), srate),
+        k35,
+    }
 });
 
-chugin::dtor!(dtor, DATA_OFFSET, Korg35, _obj, {});
+chugin::dtor!(dtor, DATA_OFFSET, Korg35Chugin, _obj, {});
 
-chugin::tick!(tick, DATA_OFFSET, Korg35, obj, inp, { 
-    obj.tick(inp as Float) as f32
+chugin::tick!(tick, DATA_OFFSET, Korg35Chugin, obj, inp, smoothed [freq, k], {
+    if obj.freq.changed() || obj.k.changed() {
+        obj.k35.set(obj.freq.value(), obj.k.value());
+    }
+    obj.k35.tick(inp as Float) as f32
 });
 
-chugin::mfun_setter_getter_float!(
-    set_freq,
-    get_freq,
-    DATA_OFFSET,
-    Korg35,
-    k35,
-    freq,
-    {
-        k35.set(freq as Float, k35.get_K() );
-    },
-    { k35.get_freq() }
-);
+chugin::mfun_setter_getter_smoothed!(set_freq, get_freq, DATA_OFFSET, Korg35Chugin, obj, freq, FREQ);
 
-chugin::mfun_setter_getter_float!(
-    set_k,
-    get_k,
-    DATA_OFFSET,
-    Korg35,
-    k35,
-    k,
-    {
-        k35.set(k35.get_freq(), k as Float );
-    },
-    { k35.get_K() }
-);
+chugin::mfun_setter_getter_smoothed!(set_k, get_k, DATA_OFFSET, Korg35Chugin, obj, k, K);
 
 fn ck_query_impl(query: *mut chuck::DL_Query) -> chugin::CKResult {
     let q = chugin::Query::new(query)?;
 
-    q.begin_class("Korg35", "UGen")?;
-
-    q.add_ctor(Some(ctor))?;
-    q.add_dtor(Some(dtor))?;
-
-    let offset = q.add_mvar("int", "@data", false)? as usize;
-    unsafe { DATA_OFFSET = offset; }
-
-    q.add_ugen_func(Some(tick), 1, 1)?;
-
-    q.add_mfun(
-        Some(set_freq),
-        "float",
-        "freq",
-        &[(String::from("float"), String::from("f"))],
-    )?;
-
-    q.add_mfun(
-        Some(set_k),
-        "float",
-        "K",
-        &[(String::from("float"), String::from("K"))],
-    )?;
-
-    q.end_class()?;
+    q.class("Korg35")
+        .extends_ugen("UGen")
+        .ctor(Some(ctor))
+        .dtor(Some(dtor))
+        .data(&DATA_OFFSET)
+        .ugen(Some(tick), 1, 1)
+        .param(Some(set_freq), Some(get_freq), &FREQ)
+        .param(Some(set_k), Some(get_k), &K)
+        .presets()
+        .finish()?;
 
     Ok(())
 }
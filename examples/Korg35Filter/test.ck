@@ -10,3 +10,7 @@ while (now < later) {
     1500 + 1400*Math.sin(2*pi*(now/second)*lfo) => filter.freq;
     20::ms => now;
 }
+
+filter.savePreset("korg35.json");
+<<< filter.presetString() >>>;
+filter.loadPreset("korg35.json");
@@ -0,0 +1,217 @@
+use chugin;
+use chugin::chuck;
+use chugin::{ChuginBase, ChuginClass, ChuginCtor, ChuginObject, ChuginSubclass, Context, This};
+use std::f64::consts::PI;
+
+static DATA_OFFSET: chugin::DataOffset = chugin::DataOffset::new();
+
+/// Filters that can extend RustFilter
+trait Filter: chugin::AsAny {
+    /// Recompute coefficients
+    fn update(&mut self, freq: chuck::Float, q: chuck::Float);
+
+    fn tick(&mut self, x: f32) -> f32;
+}
+
+/// Data of RustFilter, shared by all subclasses
+struct RustFilter {
+    freq: chuck::Float,
+    q: chuck::Float,
+    bypass: bool,
+    filter: Box<dyn Filter>,
+}
+
+impl RustFilter {
+    fn new(filter: impl Filter + 'static) -> RustFilter {
+        let mut base = RustFilter {
+            freq: 1000.0,
+            q: 0.707,
+            bypass: false,
+            filter: Box::new(filter),
+        };
+        base.filter.update(base.freq, base.q);
+        base
+    }
+
+    pub fn set_freq(&mut self, freq: chuck::Float) -> chuck::Float {
+        self.freq = freq.max(0.0);
+        self.filter.update(self.freq, self.q);
+        self.freq
+    }
+
+    pub fn set_q(&mut self, q: chuck::Float) -> chuck::Float {
+        self.q = q.max(0.1);
+        self.filter.update(self.freq, self.q);
+        self.q
+    }
+
+    pub fn tick(&mut self, x: f32) -> f32 {
+        let y = self.filter.tick(x);
+        if self.bypass {
+            x
+        } else {
+            y
+        }
+    }
+}
+
+impl ChuginObject for RustFilter {
+    fn data_offset() -> &'static chugin::DataOffset {
+        &DATA_OFFSET
+    }
+}
+
+impl ChuginClass for RustFilter {
+    const CLASS_NAME: &'static str = "RustFilter";
+}
+
+impl ChuginBase for RustFilter {
+    fn downcast_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.filter.as_any_mut().downcast_mut()
+    }
+}
+
+/// One-pole lowpass; ignores Q
+struct OnePole {
+    srate: chuck::Float,
+    a: f32,
+    y: f32,
+}
+
+impl Filter for OnePole {
+    fn update(&mut self, freq: chuck::Float, _q: chuck::Float) {
+        self.a = (1.0 - (-2.0 * PI * freq / self.srate).exp()) as f32;
+    }
+
+    fn tick(&mut self, x: f32) -> f32 {
+        self.y += self.a * (x - self.y);
+        self.y
+    }
+}
+
+impl ChuginClass for OnePole {
+    const CLASS_NAME: &'static str = "RustOnePole";
+}
+
+impl ChuginCtor for OnePole {
+    fn new(ctx: &Context) -> Self {
+        OnePole {
+            srate: ctx.srate().unwrap_or(44100) as chuck::Float,
+            a: 1.0,
+            y: 0.0,
+        }
+    }
+}
+
+impl ChuginSubclass for OnePole {
+    type Base = RustFilter;
+
+    fn into_base(self) -> RustFilter {
+        RustFilter::new(self)
+    }
+}
+
+/// Four one-pole stages with resonant feedback and input drive
+struct MoogLadder {
+    srate: chuck::Float,
+    a: f32,
+    k: f32,
+    drive: f32,
+    stages: [f32; 4],
+}
+
+impl Filter for MoogLadder {
+    fn update(&mut self, freq: chuck::Float, q: chuck::Float) {
+        self.a = (1.0 - (-2.0 * PI * freq / self.srate).exp()) as f32;
+        // self-oscillates at k = 4
+        self.k = (4.0 * (1.0 - 0.5 / q)).clamp(0.0, 3.99) as f32;
+    }
+
+    fn tick(&mut self, x: f32) -> f32 {
+        let mut input = (x * self.drive - self.k * self.stages[3]).tanh();
+
+        for stage in self.stages.iter_mut() {
+            *stage += self.a * (input - *stage);
+            input = *stage;
+        }
+
+        self.stages[3]
+    }
+}
+
+impl ChuginClass for MoogLadder {
+    const CLASS_NAME: &'static str = "RustMoogLadder";
+}
+
+impl ChuginCtor for MoogLadder {
+    fn new(ctx: &Context) -> Self {
+        MoogLadder {
+            srate: ctx.srate().unwrap_or(44100) as chuck::Float,
+            a: 1.0,
+            k: 0.0,
+            drive: 1.0,
+            stages: [0.0; 4],
+        }
+    }
+}
+
+impl ChuginSubclass for MoogLadder {
+    type Base = RustFilter;
+
+    fn into_base(self) -> RustFilter {
+        RustFilter::new(self)
+    }
+}
+
+/// Shared by all filter classes; objects of RustFilter itself have no data
+/// and pass their input through
+extern "C" fn tick(
+    ck_self: *mut chuck::Object,
+    inp: f32,
+    out: *mut f32,
+    _api: chuck::CK_DL_API,
+) -> chuck::t_CKBOOL {
+    unsafe {
+        *out = RustFilter::with_data(ck_self, |f| f.tick(inp)).unwrap_or(inp);
+    }
+
+    chuck::CK_TRUE
+}
+
+fn ck_query_impl(query: *mut chuck::DL_Query) -> chugin::CKResult {
+    let q = chugin::Query::new(query)?;
+
+    q.class(RustFilter::CLASS_NAME)
+        .extends_ugen("UGen")
+        .base::<RustFilter>()
+        .ugen(Some(tick), 1, 1)
+        .method("freq", |f: &mut RustFilter, freq: chuck::Float| f.set_freq(freq))
+        .method("freq", |f: &mut RustFilter| f.freq)
+        .method("Q", |f: &mut RustFilter, q: chuck::Float| f.set_q(q))
+        .method("Q", |f: &mut RustFilter| f.q)
+        .method("bypass", |f: &mut RustFilter, bypass: chuck::Int| {
+            f.bypass = bypass != 0;
+            This
+        })
+        .method("bypass", |f: &mut RustFilter| f.bypass as chuck::Int)
+        .finish()?;
+
+    q.class(OnePole::CLASS_NAME)
+        .extends_ugen(RustFilter::CLASS_NAME)
+        .subclass::<OnePole>()
+        .ugen(Some(tick), 1, 1)
+        .finish()?;
+
+    q.class(MoogLadder::CLASS_NAME)
+        .extends_ugen(RustFilter::CLASS_NAME)
+        .subclass::<MoogLadder>()
+        .ugen(Some(tick), 1, 1)
+        .method("drive", |f: &mut MoogLadder, drive: chuck::Float| {
+            f.drive = drive.max(0.0) as f32;
+            f.drive as chuck::Float
+        })
+        .method("drive", |f: &mut MoogLadder| f.drive as chuck::Float)
+        .finish()
+}
+
+chugin::query!(query, ck_query_impl(query));
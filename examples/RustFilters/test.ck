@@ -0,0 +1,16 @@
+Noise n => RustMoogLadder ladder => dac;
+0.3 => ladder.gain;
+800 => ladder.freq;
+2 => ladder.Q;
+4 => ladder.drive;
+<<< ladder.freq(), ladder.Q(), ladder.drive(), ladder.bypass() >>>;
+
+1::second => now;
+
+ladder =< dac;
+n => RustOnePole pole => dac;
+0.3 => pole.gain;
+pole.bypass(1).bypass(0);
+200 => pole.freq;
+
+1::second => now;
@@ -0,0 +1,159 @@
+use chugin;
+use chugin::chuck;
+use chugin::{Instance, This};
+
+static DATA_OFFSET: chugin::DataOffset = chugin::DataOffset::new();
+static STATE_OFFSET: chugin::DataOffset = chugin::DataOffset::new();
+
+/// One step taken by a chain
+#[derive(Default)]
+struct MarkovState {
+    id: chuck::Int,
+    /// number of successors the state had when it was reached
+    successors: chuck::Int,
+}
+
+/// First-order Markov chain over integer states
+#[derive(Default)]
+struct MarkovChain {
+    /// successors seen for each state, once per observation
+    transitions: Vec<Vec<chuck::Int>>,
+    state: chuck::Int,
+    seed: u64,
+}
+
+impl MarkovChain {
+    pub fn add(&mut self, from: chuck::Int, to: chuck::Int) {
+        if from < 0 || to < 0 {
+            return;
+        }
+
+        let from = from as usize;
+        if self.transitions.len() <= from {
+            self.transitions.resize(from + 1, Vec::new());
+        }
+        self.transitions[from].push(to);
+    }
+
+    /// xorshift64, enough to pick successors
+    fn random(&mut self, n: usize) -> usize {
+        if self.seed == 0 {
+            self.seed = 0x2545_f491_4f6c_dd1d;
+        }
+
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed % n as u64) as usize
+    }
+
+    fn successors(&self, state: chuck::Int) -> chuck::Int {
+        self.transitions
+            .get(state as usize)
+            .map_or(0, |successors| successors.len() as chuck::Int)
+    }
+
+    /// Move to a random successor of the current state; a state without
+    /// successors is kept
+    pub fn next(&mut self) -> chuck::Int {
+        let count = match self.transitions.get(self.state as usize) {
+            Some(successors) if !successors.is_empty() => successors.len(),
+            _ => return self.state,
+        };
+
+        let i = self.random(count);
+        self.state = self.transitions[self.state as usize][i];
+        self.state
+    }
+}
+
+impl chugin::ChuginObject for MarkovChain {
+    fn data_offset() -> &'static chugin::DataOffset {
+        &DATA_OFFSET
+    }
+}
+
+impl chugin::ChuginClass for MarkovChain {
+    const CLASS_NAME: &'static str = "MarkovChain";
+}
+
+impl chugin::ChuginCtor for MarkovChain {
+    fn new(_ctx: &chugin::Context) -> Self {
+        MarkovChain::default()
+    }
+}
+
+impl chugin::ChuginObject for MarkovState {
+    fn data_offset() -> &'static chugin::DataOffset {
+        &STATE_OFFSET
+    }
+}
+
+impl chugin::ChuginClass for MarkovState {
+    const CLASS_NAME: &'static str = "MarkovState";
+}
+
+impl chugin::ChuginCtor for MarkovState {
+    fn new(_ctx: &chugin::Context) -> Self {
+        MarkovState::default()
+    }
+}
+
+chugin::mfun_typed!(clear, DATA_OFFSET, MarkovChain, obj, (), return_, ctx, {
+    obj.transitions.clear();
+    unsafe { ctx.set_return(return_, This) };
+});
+
+chugin::mfun_typed!(step, DATA_OFFSET, MarkovChain, obj, (), return_, ctx, {
+    let id = obj.next();
+    let state = MarkovState {
+        id,
+        successors: obj.successors(id),
+    };
+
+    let state = match ctx.create_instance(state) {
+        Ok(state) => Some(state),
+        Err(e) => {
+            chugin::ck_eprintln!("MarkovChain.step: {}", e);
+            None
+        }
+    };
+    unsafe { ctx.set_return(return_, state) };
+});
+
+/// Replace the transitions with a copy of another chain's
+fn copy_from(chain: &mut MarkovChain, other: Option<Instance<MarkovChain>>) -> This {
+    // other.with would fail to borrow if other is this same chain, which
+    // has nothing to copy anyway
+    if let Some(transitions) = other.and_then(|other| other.with(|o| o.transitions.clone()).ok()) {
+        chain.transitions = transitions;
+    }
+    This
+}
+
+fn ck_query_impl(query: *mut chuck::DL_Query) -> chugin::CKResult {
+    let q = chugin::Query::new(query)?;
+
+    q.object_class::<MarkovState>()
+        .method("id", |s: &mut MarkovState| s.id)
+        .method("successors", |s: &mut MarkovState| s.successors)
+        .finish()?;
+
+    q.object_class::<MarkovChain>()
+        .method("add", |m: &mut MarkovChain, from: chuck::Int, to: chuck::Int| {
+            m.add(from, to);
+            This
+        })
+        .method("state", |m: &mut MarkovChain, state: chuck::Int| {
+            m.state = state;
+            This
+        })
+        .method("state", |m: &mut MarkovChain| m.state)
+        .method("next", |m: &mut MarkovChain| m.next())
+        .method("copyFrom", copy_from)
+        .typed_mfun(clear, "MarkovChain", "clear", chugin::args![])
+        .typed_mfun(step, "MarkovState", "step", chugin::args![])
+        .finish()
+}
+
+chugin::query!(query, ck_query_impl(query));
@@ -0,0 +1,15 @@
+MarkovChain chain;
+
+chain.add(0, 1).add(1, 2).add(1, 0).add(2, 0).state(0);
+
+repeat (8) {
+    <<< "state", chain.next() >>>;
+}
+
+MarkovChain copy;
+copy.copyFrom(chain).state(1);
+
+repeat (4) {
+    copy.step() @=> MarkovState state;
+    <<< "step", state.id(), state.successors() >>>;
+}
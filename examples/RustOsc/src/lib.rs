@@ -1,7 +1,10 @@
 use chugin;
 use chugin::chuck;
 
-static mut DATA_OFFSET: usize = 0;
+static DATA_OFFSET: chugin::DataOffset = chugin::DataOffset::new();
+
+/// offset added to the output, set from ChucK with `osc.dc`
+static DC: chugin::MemberVar<chuck::Float> = chugin::MemberVar::new("dc");
 
 /// data for the actual object itself
 #[derive(Debug)]
@@ -38,6 +41,11 @@ impl MyChugin {
         self.freq
     }
 
+    pub fn set_amp(&mut self, amp: f32) -> f32 {
+        self.amp = amp;
+        self.amp
+    }
+
     pub fn tick(&mut self) -> f32 {
         let y = -1.0 + self.phase * 2.0;
         self.phase += self.phase_update;
@@ -46,16 +54,38 @@ impl MyChugin {
         }
         y * self.amp
     }
+
+    pub fn pmsg(&mut self, msg: &str) -> bool {
+        match msg {
+            "print" => {
+                chugin::ck_println!("{:?}", self);
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
-chugin::ctor!(ctor, DATA_OFFSET, {
-    let obj = MyChugin::new(44100.0, 200.0, 1.0);
+impl chugin::ChuginObject for MyChugin {
+    fn data_offset() -> &'static chugin::DataOffset {
+        &DATA_OFFSET
+    }
+}
+
+chugin::ctor!(ctor, DATA_OFFSET, ctx, {
+    let srate = ctx.srate().unwrap_or(44100) as f32;
+    let obj = MyChugin::new(srate, 200.0, 1.0);
     obj
 });
 
 chugin::dtor!(dtor, DATA_OFFSET, MyChugin, obj, {});
 
-chugin::tick!(tick, DATA_OFFSET, MyChugin, obj, _inp, { obj.tick() });
+chugin::tick!(tick, DATA_OFFSET, MyChugin, obj, _inp, this, {
+    // DC was added to this class, so `this` has it
+    obj.tick() + unsafe { DC.get(this) } as f32
+});
+
+chugin::pmsg!(pmsg, DATA_OFFSET, MyChugin);
 
 chugin::mfun_setter_getter_float!(
     set_freq,
@@ -70,6 +100,10 @@ chugin::mfun_setter_getter_float!(
     { obj.get_freq() }
 );
 
+chugin::mfun_typed!(set_amp, DATA_OFFSET, MyChugin, obj, (amp: float), return_, {
+    unsafe { (*return_).v_float = obj.set_amp(amp as f32) as f64 };
+});
+
 fn ck_query_impl(query: *mut chuck::DL_Query) -> chugin::CKResult {
     let q = chugin::Query::new(query)?;
 
@@ -78,18 +112,17 @@ fn ck_query_impl(query: *mut chuck::DL_Query) -> chugin::CKResult {
     q.add_ctor(Some(ctor))?;
     q.add_dtor(Some(dtor))?;
 
-    let offset = q.add_mvar("int", "@data", false)? as usize;
-    unsafe {
-        DATA_OFFSET = offset;
-    }
+    q.add_data_mvar(&DATA_OFFSET)?;
+    q.add_typed_mvar(&DC)?;
+
+    q.add_ugen_func(Some(tick), Some(pmsg), 0, 1)?;
 
-    q.add_ugen_func(Some(tick), 0, 1)?;
+    q.add_setter_getter("freq", Some(set_freq), Some(get_freq))?;
 
-    q.add_mfun(
-        Some(set_freq),
-        "float",
-        "freq",
-        &[(String::from("float"), String::from("f"))],
+    q.add_overloads(
+        &chugin::Overloads::new("amp")
+            .typed_mfun(set_amp, "float", chugin::args![a: float])
+            .method(|s: &mut MyChugin| s.amp),
     )?;
 
     q.end_class()?;
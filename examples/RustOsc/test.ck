@@ -1,5 +1,6 @@
-
 RustOsc osc => dac;
 400 => osc.freq;
+0.1 => osc.dc;
+<<< osc.freq(), osc.amp(), osc.dc >>>;
 
 2::second => now;
@@ -0,0 +1,91 @@
+use chugin;
+use chugin::chuck;
+use chugin::event::{self, EventReceiver};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+static DATA_OFFSET: chugin::DataOffset = chugin::DataOffset::new();
+
+/// Time between ticks
+const PERIOD: Duration = Duration::from_millis(250);
+
+/// Event that counts upward on a background thread, broadcasting each value
+struct Ticker {
+    rx: EventReceiver<i64>,
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Ticker {
+    pub fn new(event: chugin::GlobalEvent) -> Ticker {
+        let (tx, rx) = event::channel(event);
+        let running = Arc::new(AtomicBool::new(true));
+
+        let thread_running = running.clone();
+        let thread = thread::spawn(move || {
+            let mut count = 0;
+            let mut next = Instant::now() + PERIOD;
+
+            // parked rather than asleep, so that drop can wake it
+            while thread_running.load(Ordering::Acquire) {
+                let now = Instant::now();
+                if now < next {
+                    thread::park_timeout(next - now);
+                    continue;
+                }
+
+                tx.send(count);
+                count += 1;
+                next += PERIOD;
+            }
+        });
+
+        Ticker {
+            rx: rx,
+            running: running,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn recv(&self) -> i64 {
+        self.rx.recv().unwrap_or(-1)
+    }
+}
+
+impl Drop for Ticker {
+    fn drop(&mut self) {
+        // the thread broadcasts on the ChucK object, so stop it before the
+        // object goes away
+        self.running.store(false, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+chugin::event_ctor!(ctor, DATA_OFFSET, event, { Ticker::new(event) });
+
+chugin::dtor!(dtor, DATA_OFFSET, Ticker, _obj, {});
+
+chugin::mfun_typed!(recv, DATA_OFFSET, Ticker, obj, (), return_, {
+    unsafe { (*return_).v_int = obj.recv() };
+});
+
+fn ck_query_impl(query: *mut chuck::DL_Query) -> chugin::CKResult {
+    let q = chugin::Query::new(query)?;
+
+    q.class("RustTicker")
+        .extends("Event")
+        .ctor(Some(ctor))
+        .dtor(Some(dtor))
+        .data(&DATA_OFFSET)
+        .typed_mfun(recv, "int", "recv", chugin::args![])
+        .finish()?;
+
+    Ok(())
+}
+
+chugin::query!(query, ck_query_impl(query));
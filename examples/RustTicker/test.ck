@@ -0,0 +1,6 @@
+RustTicker ticker;
+
+repeat (8) {
+    ticker => now;
+    <<< "tick", ticker.recv() >>>;
+}
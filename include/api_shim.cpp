@@ -0,0 +1,63 @@
+// api_shim.cpp
+// Chuck_DL_Api::Api::ObjectApi functions take std::string references, which
+// can't be constructed from Rust. These wrappers build the std::string on the
+// C++ side and call through the function pointer handed over from Rust.
+
+#include "wrapper.h"
+
+using namespace Chuck_DL_Api;
+
+typedef Type (*f_get_type)( CK_DL_API, Chuck_VM_Shred *, std::string & );
+typedef String (*f_create_string)( CK_DL_API, Chuck_VM_Shred *, std::string & );
+typedef t_CKBOOL (*f_get_mvar_int)( CK_DL_API, Object, std::string &, t_CKINT & );
+typedef t_CKBOOL (*f_get_mvar_float)( CK_DL_API, Object, std::string &, t_CKFLOAT & );
+typedef t_CKBOOL (*f_get_mvar_string)( CK_DL_API, Object, std::string &, String & );
+typedef t_CKBOOL (*f_get_mvar_object)( CK_DL_API, Object, std::string &, Object & );
+typedef t_CKBOOL (*f_set_string)( CK_DL_API, String, std::string & );
+
+extern "C" {
+
+Type chugin_api_get_type( void * f, CK_DL_API api, Chuck_VM_Shred * shred, const char * name )
+{
+    std::string s( name );
+    return ((f_get_type)f)( api, shred, s );
+}
+
+String chugin_api_create_string( void * f, CK_DL_API api, Chuck_VM_Shred * shred, const char * value )
+{
+    std::string s( value );
+    return ((f_create_string)f)( api, shred, s );
+}
+
+// also used for get_mvar_dur/get_mvar_time, which have the same signature
+t_CKBOOL chugin_api_get_mvar_float( void * f, CK_DL_API api, Object object, const char * name, t_CKFLOAT * value )
+{
+    std::string s( name );
+    return ((f_get_mvar_float)f)( api, object, s, *value );
+}
+
+t_CKBOOL chugin_api_get_mvar_int( void * f, CK_DL_API api, Object object, const char * name, t_CKINT * value )
+{
+    std::string s( name );
+    return ((f_get_mvar_int)f)( api, object, s, *value );
+}
+
+t_CKBOOL chugin_api_get_mvar_string( void * f, CK_DL_API api, Object object, const char * name, String * value )
+{
+    std::string s( name );
+    return ((f_get_mvar_string)f)( api, object, s, *value );
+}
+
+t_CKBOOL chugin_api_get_mvar_object( void * f, CK_DL_API api, Object object, const char * name, Object * value )
+{
+    std::string s( name );
+    return ((f_get_mvar_object)f)( api, object, s, *value );
+}
+
+t_CKBOOL chugin_api_set_string( void * f, CK_DL_API api, String string, const char * value )
+{
+    std::string s( value );
+    return ((f_set_string)f)( api, string, s );
+}
+
+}
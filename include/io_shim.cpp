@@ -0,0 +1,63 @@
+// io_shim.cpp
+// Writing to ChucK's chout/cherr and reading its log level and sample rate.
+// The console objects and the VM hang off the Chuck_Carrier, write() is
+// virtual and the query's srate follows std::string members, so none of them
+// can be reached from Rust through the bindings.
+
+#include "wrapper.h"
+
+// ChucK's -v verbosity; weak so chugins still load in hosts that don't
+// export it. MSVC has no weak symbols and chuck.exe doesn't export it, so
+// there the level is unknown.
+#if !defined(_MSC_VER)
+extern t_CKINT g_loglevel __attribute__((weak));
+#endif
+
+extern "C" {
+
+Chuck_VM * chugin_query_vm( Chuck_DL_Query * query )
+{
+    if( query->carrier() == NULL ) return NULL;
+    return query->vm();
+}
+
+Chuck_IO * chugin_query_chout( Chuck_DL_Query * query )
+{
+    if( query->carrier() == NULL ) return NULL;
+    return query->carrier()->chout;
+}
+
+Chuck_IO * chugin_query_cherr( Chuck_DL_Query * query )
+{
+    if( query->carrier() == NULL ) return NULL;
+    return query->carrier()->cherr;
+}
+
+// srate comes after the query's std::string members, so this relies on the
+// chugin using the host's C++ standard library, as api_shim.cpp already does
+t_CKUINT chugin_query_srate( Chuck_DL_Query * query )
+{
+    return query->srate;
+}
+
+void chugin_io_write( Chuck_IO * io, const char * s )
+{
+    io->write( std::string( s ) );
+}
+
+void chugin_io_flush( Chuck_IO * io )
+{
+    io->flush();
+}
+
+// -1 if the host doesn't export its log level
+t_CKINT chugin_log_level()
+{
+#if defined(_MSC_VER)
+    return -1;
+#else
+    return &g_loglevel != NULL ? g_loglevel : -1;
+#endif
+}
+
+}
@@ -0,0 +1,108 @@
+// object_shim.cpp
+// Access to Chuck_VM_Object reference counting, Chuck_Array contents and
+// Chuck_Event signalling, plus type checks. add_ref/release/size are virtual,
+// the array storage is a std::vector and the event methods are C++ members,
+// none of which can be reached from Rust through the bindings.
+// NOTE: the event functions and isa resolve against the host's symbols when
+// the chugin is loaded.
+
+#include "wrapper.h"
+
+// defined in the host's chuck_type.cpp; Chuck_Type itself is only forward
+// declared here, which is all a call needs
+t_CKBOOL isa( Chuck_Type * lhs, Chuck_Type * rhs );
+
+extern "C" {
+
+void chugin_object_add_ref( Chuck_VM_Object * obj )
+{
+    obj->add_ref();
+}
+
+void chugin_object_release( Chuck_VM_Object * obj )
+{
+    obj->release();
+}
+
+// like release, but an object left without references isn't deleted; used
+// when handing an object back to ChucK, which expects native functions to
+// return objects without a reference of their own (as it creates them)
+void chugin_object_unref( Chuck_VM_Object * obj )
+{
+    if( obj->m_ref_count > 0 ) obj->m_ref_count--;
+}
+
+t_CKINT chugin_array4_size( Chuck_Array4 * arr )
+{
+    return arr->size();
+}
+
+t_CKBOOL chugin_array4_get( Chuck_Array4 * arr, t_CKINT i, t_CKINT * val )
+{
+    if( arr->m_is_obj || i < 0 || i >= (t_CKINT)arr->m_vector.size() ) return FALSE;
+    *val = (t_CKINT)arr->m_vector[i];
+    return TRUE;
+}
+
+t_CKBOOL chugin_array4_set( Chuck_Array4 * arr, t_CKINT i, t_CKINT val )
+{
+    // object arrays need reference counting on set; not supported
+    if( arr->m_is_obj || i < 0 || i >= (t_CKINT)arr->m_vector.size() ) return FALSE;
+    arr->m_vector[i] = (t_CKUINT)val;
+    return TRUE;
+}
+
+t_CKBOOL chugin_array4_push_back( Chuck_Array4 * arr, t_CKINT val )
+{
+    if( arr->m_is_obj ) return FALSE;
+    arr->m_vector.push_back( (t_CKUINT)val );
+    return TRUE;
+}
+
+t_CKINT chugin_array8_size( Chuck_Array8 * arr )
+{
+    return arr->size();
+}
+
+t_CKBOOL chugin_array8_get( Chuck_Array8 * arr, t_CKINT i, t_CKFLOAT * val )
+{
+    if( i < 0 || i >= (t_CKINT)arr->m_vector.size() ) return FALSE;
+    *val = arr->m_vector[i];
+    return TRUE;
+}
+
+t_CKBOOL chugin_array8_set( Chuck_Array8 * arr, t_CKINT i, t_CKFLOAT val )
+{
+    if( i < 0 || i >= (t_CKINT)arr->m_vector.size() ) return FALSE;
+    arr->m_vector[i] = val;
+    return TRUE;
+}
+
+void chugin_array8_push_back( Chuck_Array8 * arr, t_CKFLOAT val )
+{
+    arr->m_vector.push_back( val );
+}
+
+void chugin_event_signal_local( Chuck_Event * event )
+{
+    event->signal_local();
+}
+
+void chugin_event_broadcast_local( Chuck_Event * event )
+{
+    event->broadcast_local();
+}
+
+// safe from any thread: the waiting shreds are handed to the VM through
+// the buffer and woken on the VM thread
+void chugin_event_queue_broadcast( Chuck_Event * event, CBufferSimple * buffer )
+{
+    event->queue_broadcast( buffer );
+}
+
+t_CKBOOL chugin_type_isa( Chuck_Type * lhs, Chuck_Type * rhs )
+{
+    return isa( lhs, rhs );
+}
+
+}
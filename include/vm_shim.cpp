@@ -0,0 +1,48 @@
+// vm_shim.cpp
+// Reading the VM's logical time and a shred's id, and the event buffers
+// through which other threads queue broadcasts. chuck_vm.h isn't part of
+// the DL headers, so Chuck_VM and Chuck_VM_Shred are only forward declared
+// and their members can't be reached from Rust through the bindings.
+// NOTE: the declarations below mirror ChucK 1.4.1's chuck_vm.h and
+// chuck_lang.h as far as these wrappers need them; the member functions and
+// shred_id resolve against the host's symbols when the chugin is loaded.
+
+#include "wrapper.h"
+
+struct Chuck_VM_Shreduler : Chuck_Object
+{
+    // the VM's logical time, its first member
+    t_CKTIME now_system;
+};
+
+struct Chuck_VM
+{
+    Chuck_VM_Shreduler * shreduler() const;
+    CBufferSimple * create_event_buffer();
+};
+
+// Shred.id(), defined in the host's chuck_lang.cpp
+CK_DLL_MFUN( shred_id );
+
+extern "C" {
+
+t_CKTIME chugin_vm_now( Chuck_VM * vm )
+{
+    return vm->shreduler()->now_system;
+}
+
+// must be called on the VM thread, which drains the buffer
+CBufferSimple * chugin_vm_create_event_buffer( Chuck_VM * vm )
+{
+    return vm->create_event_buffer();
+}
+
+t_CKINT chugin_shred_id( Chuck_VM_Shred * shred )
+{
+    Chuck_DL_Return ret;
+    ret.v_int = 0;
+    shred_id( reinterpret_cast<Chuck_Object *>( shred ), NULL, &ret, NULL, shred, NULL );
+    return ret.v_int;
+}
+
+}
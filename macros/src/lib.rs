@@ -22,7 +22,10 @@ pub fn query_fn(_metadata: TokenStream, input: TokenStream) -> TokenStream {
             pub extern "C" fn ck_query(query: *mut chuck::DL_Query) -> chuck::t_CKBOOL {    
                 match #input_fn_name (query) {
                     Ok(_) => chuck::CK_TRUE,
-                    Err(_) => chuck::CK_FALSE,
+                    Err(e) => {
+                        chugin::ck_eprintln!("[{}]: {}", env!("CARGO_PKG_NAME"), e);
+                        chuck::CK_FALSE
+                    }
                 }
             }
         })
@@ -0,0 +1,392 @@
+// Safe wrapper for the ChucK DL Api passed to chugin callbacks
+
+use crate::chuck;
+use crate::context::Shred;
+use crate::cstring::CString;
+use crate::{CKResult, Error};
+use std::os::raw::{c_char, c_void};
+
+// ObjectApi functions take std::string references, which can't be built
+// from Rust; these C++ wrappers (include/api_shim.cpp) build the string and
+// call through the function pointer passed as `f`
+extern "C" {
+    fn chugin_api_get_type(
+        f: *const c_void,
+        api: chuck::CK_DL_API,
+        shred: *mut chuck::VM_Shred,
+        name: *const c_char,
+    ) -> chuck::DL_Api_Type;
+    fn chugin_api_create_string(
+        f: *const c_void,
+        api: chuck::CK_DL_API,
+        shred: *mut chuck::VM_Shred,
+        value: *const c_char,
+    ) -> chuck::DL_Api_String;
+    fn chugin_api_get_mvar_int(
+        f: *const c_void,
+        api: chuck::CK_DL_API,
+        object: chuck::DL_Api_Object,
+        name: *const c_char,
+        value: *mut chuck::Int,
+    ) -> chuck::t_CKBOOL;
+    fn chugin_api_get_mvar_float(
+        f: *const c_void,
+        api: chuck::CK_DL_API,
+        object: chuck::DL_Api_Object,
+        name: *const c_char,
+        value: *mut chuck::Float,
+    ) -> chuck::t_CKBOOL;
+    fn chugin_api_get_mvar_string(
+        f: *const c_void,
+        api: chuck::CK_DL_API,
+        object: chuck::DL_Api_Object,
+        name: *const c_char,
+        value: *mut chuck::DL_Api_String,
+    ) -> chuck::t_CKBOOL;
+    fn chugin_api_get_mvar_object(
+        f: *const c_void,
+        api: chuck::CK_DL_API,
+        object: chuck::DL_Api_Object,
+        name: *const c_char,
+        value: *mut chuck::DL_Api_Object,
+    ) -> chuck::t_CKBOOL;
+    fn chugin_api_set_string(
+        f: *const c_void,
+        api: chuck::CK_DL_API,
+        string: chuck::DL_Api_String,
+        value: *const c_char,
+    ) -> chuck::t_CKBOOL;
+}
+
+// The DL 8.0 Api has no release or type functions; ChucK only added those to
+// the Api in later DL versions, which this crate doesn't target. These reach
+// the host directly through include/object_shim.cpp instead
+extern "C" {
+    fn chugin_object_add_ref(obj: *mut chuck::VM_Object);
+    fn chugin_object_release(obj: *mut chuck::VM_Object);
+    fn chugin_type_isa(lhs: *mut chuck::Type, rhs: *mut chuck::Type) -> chuck::t_CKBOOL;
+}
+
+/// Handle to the ChucK DL Api
+#[derive(Clone, Copy)]
+pub struct Api {
+    api: chuck::CK_DL_API,
+}
+
+impl Api {
+    /// Create new wrapper from ChucK type
+    pub fn new(api: chuck::CK_DL_API) -> Api {
+        Api { api: api }
+    }
+
+    /// Underlying ChucK pointer
+    pub fn as_ptr(&self) -> chuck::CK_DL_API {
+        self.api
+    }
+
+    fn vm_api(&self) -> CKResult<&chuck::DL_VMApi> {
+        let api = match unsafe { self.api.as_ref() } {
+            Some(api) => api,
+            None => return Err(Error::NullPointer("api")),
+        };
+
+        match unsafe { api.vm.as_ref() } {
+            Some(vm) => Ok(vm),
+            None => Err(Error::NullPointer("api vm")),
+        }
+    }
+
+    fn object_api(&self) -> CKResult<&chuck::DL_ObjectApi> {
+        let api = match unsafe { self.api.as_ref() } {
+            Some(api) => api,
+            None => return Err(Error::NullPointer("api")),
+        };
+
+        match unsafe { api.object.as_ref() } {
+            Some(object) => Ok(object),
+            None => Err(Error::NullPointer("api object")),
+        }
+    }
+
+    /// Sample rate of the VM running the shred
+    pub fn srate(&self, shred: Shred) -> CKResult<chuck::UInt> {
+        // ChucK dereferences the shred to find its VM
+        if shred.as_ptr().is_null() {
+            return Err(Error::NullPointer("shred"));
+        }
+
+        let get_srate = match self.vm_api()?.get_srate {
+            Some(f) => f,
+            None => return Err(Error::MissingApiFn("get_srate")),
+        };
+
+        Ok(unsafe { get_srate(self.api, shred.as_ptr()) })
+    }
+
+    /// Instantiate a new ChucK object of the given type
+    pub fn create(&self, shred: Shred, type_: *mut chuck::Type) -> CKResult<*mut chuck::Object> {
+        if type_.is_null() {
+            return Err(Error::NullPointer("type"));
+        }
+
+        let create = match self.object_api()?.create {
+            Some(f) => f,
+            None => return Err(Error::MissingApiFn("create")),
+        };
+
+        let obj = unsafe { create(self.api, shred.as_ptr(), type_ as chuck::DL_Api_Type) };
+
+        if !obj.is_null() {
+            Ok(obj as *mut chuck::Object)
+        } else {
+            Err(Error::NullPointer("created object"))
+        }
+    }
+
+    /// Look up a ChucK type by name
+    pub fn get_type(&self, shred: Shred, name: &str) -> CKResult<*mut chuck::Type> {
+        let name = CString::new("type name", name)?;
+
+        let get_type = match self.object_api()?.get_type {
+            Some(f) => f,
+            None => return Err(Error::MissingApiFn("get_type")),
+        };
+
+        let type_ = unsafe {
+            chugin_api_get_type(
+                get_type as *const c_void,
+                self.api,
+                shred.as_ptr(),
+                name.c_str(),
+            )
+        };
+
+        if !type_.is_null() {
+            Ok(type_ as *mut chuck::Type)
+        } else {
+            Err(Error::ApiCallFailed("get_type"))
+        }
+    }
+
+    /// Instantiate a new ChucK string
+    pub fn create_string(&self, shred: Shred, value: &str) -> CKResult<*mut chuck::String> {
+        let value = CString::new("string value", value)?;
+
+        let create_string = match self.object_api()?.create_string {
+            Some(f) => f,
+            None => return Err(Error::MissingApiFn("create_string")),
+        };
+
+        let string = unsafe {
+            chugin_api_create_string(
+                create_string as *const c_void,
+                self.api,
+                shred.as_ptr(),
+                value.c_str(),
+            )
+        };
+
+        if !string.is_null() {
+            Ok(string as *mut chuck::String)
+        } else {
+            Err(Error::ApiCallFailed("create_string"))
+        }
+    }
+
+    /// Read an int member variable of a ChucK object by name
+    pub fn get_mvar_int(&self, object: *mut chuck::Object, name: &str) -> CKResult<chuck::Int> {
+        let name = CString::new("member variable name", name)?;
+
+        let get_mvar_int = match self.object_api()?.get_mvar_int {
+            Some(f) => f,
+            None => return Err(Error::MissingApiFn("get_mvar_int")),
+        };
+
+        let mut value: chuck::Int = 0;
+
+        match unsafe {
+            chugin_api_get_mvar_int(
+                get_mvar_int as *const c_void,
+                self.api,
+                object as chuck::DL_Api_Object,
+                name.c_str(),
+                &mut value,
+            )
+        } {
+            0 => Err(Error::ApiCallFailed("get_mvar_int")),
+            _ => Ok(value),
+        }
+    }
+
+    /// Read a float member variable of a ChucK object by name
+    pub fn get_mvar_float(&self, object: *mut chuck::Object, name: &str) -> CKResult<chuck::Float> {
+        let f = self.object_api()?.get_mvar_float.map(|f| f as *const c_void);
+        self.get_mvar_f64(f, "get_mvar_float", object, name)
+    }
+
+    /// Read a dur member variable of a ChucK object by name
+    pub fn get_mvar_dur(&self, object: *mut chuck::Object, name: &str) -> CKResult<chuck::Float> {
+        let f = self.object_api()?.get_mvar_dur.map(|f| f as *const c_void);
+        self.get_mvar_f64(f, "get_mvar_dur", object, name)
+    }
+
+    /// Read a time member variable of a ChucK object by name
+    pub fn get_mvar_time(&self, object: *mut chuck::Object, name: &str) -> CKResult<chuck::Float> {
+        let f = self.object_api()?.get_mvar_time.map(|f| f as *const c_void);
+        self.get_mvar_f64(f, "get_mvar_time", object, name)
+    }
+
+    // float, dur and time are all doubles and share a signature
+    fn get_mvar_f64(
+        &self,
+        f: Option<*const c_void>,
+        which: &'static str,
+        object: *mut chuck::Object,
+        name: &str,
+    ) -> CKResult<chuck::Float> {
+        let name = CString::new("member variable name", name)?;
+
+        let f = match f {
+            Some(f) => f,
+            None => return Err(Error::MissingApiFn(which)),
+        };
+
+        let mut value: chuck::Float = 0.0;
+
+        match unsafe {
+            chugin_api_get_mvar_float(
+                f,
+                self.api,
+                object as chuck::DL_Api_Object,
+                name.c_str(),
+                &mut value,
+            )
+        } {
+            0 => Err(Error::ApiCallFailed(which)),
+            _ => Ok(value),
+        }
+    }
+
+    /// Read a string member variable of a ChucK object by name
+    pub fn get_mvar_string(
+        &self,
+        object: *mut chuck::Object,
+        name: &str,
+    ) -> CKResult<*mut chuck::String> {
+        let name = CString::new("member variable name", name)?;
+
+        let get_mvar_string = match self.object_api()?.get_mvar_string {
+            Some(f) => f,
+            None => return Err(Error::MissingApiFn("get_mvar_string")),
+        };
+
+        let mut value: chuck::DL_Api_String = std::ptr::null_mut();
+
+        match unsafe {
+            chugin_api_get_mvar_string(
+                get_mvar_string as *const c_void,
+                self.api,
+                object as chuck::DL_Api_Object,
+                name.c_str(),
+                &mut value,
+            )
+        } {
+            0 => Err(Error::ApiCallFailed("get_mvar_string")),
+            _ => Ok(value as *mut chuck::String),
+        }
+    }
+
+    /// Read an object member variable of a ChucK object by name
+    pub fn get_mvar_object(
+        &self,
+        object: *mut chuck::Object,
+        name: &str,
+    ) -> CKResult<*mut chuck::Object> {
+        let name = CString::new("member variable name", name)?;
+
+        let get_mvar_object = match self.object_api()?.get_mvar_object {
+            Some(f) => f,
+            None => return Err(Error::MissingApiFn("get_mvar_object")),
+        };
+
+        let mut value: chuck::DL_Api_Object = std::ptr::null_mut();
+
+        match unsafe {
+            chugin_api_get_mvar_object(
+                get_mvar_object as *const c_void,
+                self.api,
+                object as chuck::DL_Api_Object,
+                name.c_str(),
+                &mut value,
+            )
+        } {
+            0 => Err(Error::ApiCallFailed("get_mvar_object")),
+            _ => Ok(value as *mut chuck::Object),
+        }
+    }
+
+    /// Replace the contents of a ChucK string
+    pub fn set_string(&self, string: *mut chuck::String, value: &str) -> CKResult {
+        let value = CString::new("string value", value)?;
+
+        let set_string = match self.object_api()?.set_string {
+            Some(f) => f,
+            None => return Err(Error::MissingApiFn("set_string")),
+        };
+
+        match unsafe {
+            chugin_api_set_string(
+                set_string as *const c_void,
+                self.api,
+                string as chuck::DL_Api_String,
+                value.c_str(),
+            )
+        } {
+            0 => Err(Error::ApiCallFailed("set_string")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Add a reference to a ChucK object
+    /// Unsafe because object must be null or a live ChucK object
+    pub unsafe fn add_ref(&self, object: *mut chuck::Object) -> CKResult {
+        if object.is_null() {
+            return Err(Error::NullPointer("object"));
+        }
+
+        chugin_object_add_ref(object as *mut chuck::VM_Object);
+        Ok(())
+    }
+
+    /// Release a reference to a ChucK object, which is deleted once no
+    /// references are left
+    /// Unsafe because object must be null or a live ChucK object
+    pub unsafe fn release(&self, object: *mut chuck::Object) -> CKResult {
+        if object.is_null() {
+            return Err(Error::NullPointer("object"));
+        }
+
+        chugin_object_release(object as *mut chuck::VM_Object);
+        Ok(())
+    }
+
+    /// Class of a ChucK object
+    /// Unsafe because object must be null or a live ChucK object
+    pub unsafe fn type_of(&self, object: *mut chuck::Object) -> CKResult<*mut chuck::Type> {
+        match object.as_ref() {
+            Some(object) if !object.type_ref.is_null() => Ok(object.type_ref),
+            Some(_) => Err(Error::NullPointer("object type")),
+            None => Err(Error::NullPointer("object")),
+        }
+    }
+
+    /// Whether `type_` is `parent` or one of its subclasses
+    /// Unsafe because both must be null or types of a running VM
+    pub unsafe fn isa(&self, type_: *mut chuck::Type, parent: *mut chuck::Type) -> CKResult<bool> {
+        if type_.is_null() || parent.is_null() {
+            return Err(Error::NullPointer("type"));
+        }
+
+        Ok(chugin_type_isa(type_, parent) != 0)
+    }
+}
@@ -0,0 +1,153 @@
+// Typed argument lists for member functions
+
+use crate::chuck;
+use crate::cktype::CKType;
+use crate::context::Context;
+use std::marker::PhantomData;
+
+/// A type an argument list can hold: any CKType, plus types whose decoding
+/// needs the VM, like `Option<Instance<T>>`
+pub trait ArgType: Sized {
+    /// name of the corresponding type in ChucK
+    const TYPE_NAME: &'static str;
+
+    /// Decode the next argument of a callback invoked with ctx
+    fn get_next_arg_in(args: chuck::Args, ctx: &Context) -> (chuck::Args, Self);
+}
+
+impl<T: CKType> ArgType for T {
+    const TYPE_NAME: &'static str = <T as CKType>::TYPE_NAME;
+
+    fn get_next_arg_in(args: chuck::Args, _ctx: &Context) -> (chuck::Args, Self) {
+        T::get_next_arg(args)
+    }
+}
+
+/// A tuple of ChucK argument types, decoded in order from a callback's args
+pub trait ArgList: Sized {
+    /// ChucK type names of each argument, in order
+    fn type_names() -> Vec<&'static str>;
+
+    /// Number of arguments
+    fn len() -> usize {
+        Self::type_names().len()
+    }
+
+    /// Decode all arguments of a callback invoked with ctx
+    /// Unsafe because args must point to arguments of exactly these types
+    unsafe fn decode(args: chuck::Args, ctx: &Context) -> Self;
+}
+
+impl ArgList for () {
+    fn type_names() -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    unsafe fn decode(_args: chuck::Args, _ctx: &Context) -> Self {}
+}
+
+macro_rules! impl_arg_list {
+    ($($t:ident),+) => {
+        impl<$($t: ArgType),+> ArgList for ($($t,)+) {
+            fn type_names() -> Vec<&'static str> {
+                vec![$(<$t as ArgType>::TYPE_NAME),+]
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn decode(args: chuck::Args, ctx: &Context) -> Self {
+                $(
+                    let (args, $t) = <$t as ArgType>::get_next_arg_in(args, ctx);
+                )+
+                let _ = args;
+                ($($t,)+)
+            }
+        }
+    };
+}
+
+impl_arg_list!(A);
+impl_arg_list!(A, B);
+impl_arg_list!(A, B, C);
+impl_arg_list!(A, B, C, D);
+impl_arg_list!(A, B, C, D, E);
+impl_arg_list!(A, B, C, D, E, F);
+
+/// Argument names for a member function, typed by the Rust types of the
+/// arguments; created with the args! macro
+pub struct ArgSpec<A: ArgList> {
+    names: Vec<&'static str>,
+    types: PhantomData<A>,
+}
+
+impl<A: ArgList> ArgSpec<A> {
+    /// Create a spec from argument names; panics if the number of names does
+    /// not match the number of types (args! guarantees that it does)
+    pub fn new(names: &[&'static str]) -> ArgSpec<A> {
+        assert_eq!(names.len(), A::len(), "argument name/type count mismatch");
+
+        ArgSpec {
+            names: names.to_vec(),
+            types: PhantomData,
+        }
+    }
+
+    /// (type, name) pairs as expected by Query::add_mfun
+    pub fn to_pairs(&self) -> Vec<(String, String)> {
+        A::type_names()
+            .into_iter()
+            .zip(self.names.iter())
+            .map(|(type_, name)| (type_.to_string(), name.to_string()))
+            .collect()
+    }
+}
+
+/// A member function whose argument types are known; created with the
+/// mfun_typed! macro
+pub struct Mfun<A: ArgList> {
+    mfun: chuck::f_mfun,
+    types: PhantomData<A>,
+}
+
+impl<A: ArgList> Mfun<A> {
+    pub const fn new(mfun: chuck::f_mfun) -> Mfun<A> {
+        Mfun {
+            mfun,
+            types: PhantomData,
+        }
+    }
+
+    pub fn get(&self) -> chuck::f_mfun {
+        self.mfun
+    }
+}
+
+/// Map a ChucK type keyword to the Rust type it is decoded as
+#[macro_export]
+macro_rules! ck_type {
+    (float) => {
+        chuck::Float
+    };
+    (int) => {
+        chuck::Int
+    };
+    (string) => {
+        Option<chugin::CkString>
+    };
+    (Event) => {
+        Option<chugin::CkEvent>
+    };
+    (Object) => {
+        Option<chugin::CkRef<chuck::Object>>
+    };
+}
+
+/// Build a typed argument spec, e.g. `args![f: float, k: int]`
+#[macro_export]
+macro_rules! args {
+    () => {
+        chugin::args::ArgSpec::<()>::new(&[])
+    };
+    ($($name:ident : $type_:ident),+ $(,)?) => {
+        chugin::args::ArgSpec::<($(chugin::ck_type!($type_),)+)>::new(&[$(stringify!($name)),+])
+    };
+}
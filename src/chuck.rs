@@ -10,14 +10,32 @@ pub type DL_Query = Chuck_DL_Query;
 pub type DL_Api = Chuck_DL_Api_Api;
 pub type VM = Chuck_VM;
 pub type VM_Shred = Chuck_VM_Shred;
+pub type VM_Object = Chuck_VM_Object;
 pub type Object = Chuck_Object;
+pub type Type = Chuck_Type;
+pub type String = Chuck_String;
+pub type Array4 = Chuck_Array4;
+pub type Array8 = Chuck_Array8;
+pub type Array16 = Chuck_Array16;
+pub type Array24 = Chuck_Array24;
+pub type Array32 = Chuck_Array32;
+pub type Event = Chuck_Event;
+pub type DL_VMApi = Chuck_DL_Api_Api_VMApi;
+pub type DL_ObjectApi = Chuck_DL_Api_Api_ObjectApi;
+pub type DL_Api_Object = Chuck_DL_Api_Object;
+pub type DL_Api_Type = Chuck_DL_Api_Type;
+pub type DL_Api_String = Chuck_DL_Api_String;
+pub type DL_MainThreadHook = Chuck_DL_MainThreadHook;
 
 // basic ChucK types not automatically imported by bindgen (due to #define)
 pub type t_CKUINT = ::std::os::raw::c_ulong;
 pub type t_CKBOOL = ::std::os::raw::c_ulong;
+pub type Int = ::std::os::raw::c_long;
 pub type UInt = ::std::os::raw::c_ulong;
 pub type Bool = ::std::os::raw::c_ulong;
 pub type Float = f64;
+pub type Time = f64;
+pub type Dur = f64;
 pub type Args = *mut ::std::os::raw::c_void;
 
 pub const CK_TRUE: t_CKBOOL = 1;
@@ -0,0 +1,342 @@
+// Reference-counted handles to ChucK objects
+
+use crate::chuck;
+use crate::cktype::CKType;
+use crate::context::{Context, Vm};
+use crate::util;
+use crate::{CKResult, Error};
+use std::os::raw::c_void;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+
+// add_ref/release are virtual and arrays are backed by std::vector, so these
+// go through the C++ wrappers in include/object_shim.cpp
+extern "C" {
+    fn chugin_object_add_ref(obj: *mut chuck::VM_Object);
+    fn chugin_object_release(obj: *mut chuck::VM_Object);
+    fn chugin_object_unref(obj: *mut chuck::VM_Object);
+    fn chugin_array4_size(arr: *mut chuck::Array4) -> chuck::Int;
+    fn chugin_array4_get(arr: *mut chuck::Array4, i: chuck::Int, val: *mut chuck::Int) -> chuck::t_CKBOOL;
+    fn chugin_array4_set(arr: *mut chuck::Array4, i: chuck::Int, val: chuck::Int) -> chuck::t_CKBOOL;
+    fn chugin_array4_push_back(arr: *mut chuck::Array4, val: chuck::Int) -> chuck::t_CKBOOL;
+    fn chugin_array8_size(arr: *mut chuck::Array8) -> chuck::Int;
+    fn chugin_array8_get(arr: *mut chuck::Array8, i: chuck::Int, val: *mut chuck::Float) -> chuck::t_CKBOOL;
+    fn chugin_array8_set(arr: *mut chuck::Array8, i: chuck::Int, val: chuck::Float) -> chuck::t_CKBOOL;
+    fn chugin_array8_push_back(arr: *mut chuck::Array8, val: chuck::Float);
+    fn chugin_event_signal_local(event: *mut chuck::Event);
+    fn chugin_event_broadcast_local(event: *mut chuck::Event);
+    fn chugin_event_queue_broadcast(event: *mut chuck::Event, buffer: *mut c_void);
+    fn chugin_vm_create_event_buffer(vm: *mut chuck::VM) -> *mut c_void;
+}
+
+/// ChucK object types that can be held by a CkRef
+/// Unsafe because implementors must be subclasses of Chuck_VM_Object
+pub unsafe trait CkObject {
+    /// name of the type in ChucK, used when declaring arguments
+    const TYPE_NAME: &'static str;
+}
+
+unsafe impl CkObject for chuck::Object {
+    const TYPE_NAME: &'static str = "Object";
+}
+
+unsafe impl CkObject for chuck::String {
+    const TYPE_NAME: &'static str = "string";
+}
+
+unsafe impl CkObject for chuck::Array4 {
+    const TYPE_NAME: &'static str = "int[]";
+}
+
+unsafe impl CkObject for chuck::Array8 {
+    const TYPE_NAME: &'static str = "float[]";
+}
+
+unsafe impl CkObject for chuck::Array16 {
+    const TYPE_NAME: &'static str = "complex[]";
+}
+
+unsafe impl CkObject for chuck::Array24 {
+    const TYPE_NAME: &'static str = "vec3[]";
+}
+
+unsafe impl CkObject for chuck::Array32 {
+    const TYPE_NAME: &'static str = "vec4[]";
+}
+
+unsafe impl CkObject for chuck::Event {
+    const TYPE_NAME: &'static str = "Event";
+}
+
+/// Counted reference to a ChucK object
+/// Adds a reference when created or cloned and releases it when dropped, so
+/// the object stays alive for as long as Rust holds on to it
+pub struct CkRef<T: CkObject> {
+    ptr: NonNull<T>,
+}
+
+impl<T: CkObject> CkRef<T> {
+    /// Take a new reference to a ChucK object; None if ptr is null
+    /// Unsafe because ptr must point to a live object of type T
+    pub unsafe fn new(ptr: *mut T) -> Option<CkRef<T>> {
+        let ptr = NonNull::new(ptr)?;
+        chugin_object_add_ref(ptr.as_ptr() as *mut chuck::VM_Object);
+        Some(CkRef { ptr })
+    }
+
+    /// Take over a reference that was already added, e.g. one stored in a
+    /// member variable; None if ptr is null
+    /// Unsafe because ptr must point to a live object of type T whose
+    /// reference is not released elsewhere
+    pub unsafe fn from_raw(ptr: *mut T) -> Option<CkRef<T>> {
+        NonNull::new(ptr).map(|ptr| CkRef { ptr })
+    }
+
+    /// Give up the handle without releasing its reference
+    pub fn into_raw(self) -> *mut T {
+        let ptr = self.as_ptr();
+        std::mem::forget(self);
+        ptr
+    }
+
+    /// Give up the handle to return the object from a native function
+    /// ChucK doesn't take over a reference from those, so the reference is
+    /// dropped, but without deleting an object it was the last one of: it is
+    /// left unreferenced the way ChucK creates objects itself
+    pub(crate) fn into_return_value(self) -> *mut T {
+        let ptr = self.into_raw();
+        unsafe { chugin_object_unref(ptr as *mut chuck::VM_Object) };
+        ptr
+    }
+
+    /// Underlying ChucK pointer
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    /// Underlying ChucK pointer as a plain Object
+    pub fn as_object(&self) -> *mut chuck::Object {
+        self.ptr.as_ptr() as *mut chuck::Object
+    }
+}
+
+impl<T: CkObject> Clone for CkRef<T> {
+    fn clone(&self) -> Self {
+        unsafe {
+            chugin_object_add_ref(self.ptr.as_ptr() as *mut chuck::VM_Object);
+        }
+
+        CkRef { ptr: self.ptr }
+    }
+}
+
+impl<T: CkObject> Drop for CkRef<T> {
+    fn drop(&mut self) {
+        unsafe {
+            chugin_object_release(self.ptr.as_ptr() as *mut chuck::VM_Object);
+        }
+    }
+}
+
+/// CKType impl for ChucK objects; ChucK may pass null, hence the Option
+impl<T: CkObject> CKType for Option<CkRef<T>> {
+    const TYPE_NAME: &'static str = T::TYPE_NAME;
+
+    fn get_next_arg(args: chuck::Args) -> (chuck::Args, Self) {
+        let args = args as *const *mut T;
+        let arg = unsafe { CkRef::new(*args) };
+        let args = unsafe { args.offset(1) as chuck::Args };
+        (args, arg)
+    }
+}
+
+impl CkRef<chuck::Object> {
+    /// Whether the object's class is `class` or a subclass of it, looking the
+    /// class up in the VM running ctx's shred
+    pub fn is_instance_of(&self, ctx: &Context, class: &str) -> bool {
+        let class = match ctx.get_type(class) {
+            Ok(class) => class,
+            Err(_) => return false,
+        };
+
+        let api = ctx.api();
+        matches!(
+            unsafe { api.type_of(self.as_ptr()).and_then(|type_| api.isa(type_, class)) },
+            Ok(true)
+        )
+    }
+}
+
+/// Counted reference to a ChucK string
+pub type CkString = CkRef<chuck::String>;
+
+impl CkRef<chuck::String> {
+    /// Borrow the contents; None if they are not valid UTF-8
+    pub fn to_str(&self) -> Option<&str> {
+        unsafe { util::get_str((*self.as_ptr()).m_charptr) }
+    }
+}
+
+/// Counted reference to a ChucK int[]
+pub type CkIntArray = CkRef<chuck::Array4>;
+
+impl CkRef<chuck::Array4> {
+    pub fn len(&self) -> usize {
+        unsafe { chugin_array4_size(self.as_ptr()) as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// None if i is out of bounds or this is an array of objects
+    pub fn get(&self, i: usize) -> Option<chuck::Int> {
+        let mut val = 0;
+
+        match unsafe { chugin_array4_get(self.as_ptr(), i as chuck::Int, &mut val) } {
+            0 => None,
+            _ => Some(val),
+        }
+    }
+
+    /// false if i is out of bounds or this is an array of objects
+    pub fn set(&self, i: usize, val: chuck::Int) -> bool {
+        unsafe { chugin_array4_set(self.as_ptr(), i as chuck::Int, val) != 0 }
+    }
+
+    /// false if this is an array of objects
+    pub fn push(&self, val: chuck::Int) -> bool {
+        unsafe { chugin_array4_push_back(self.as_ptr(), val) != 0 }
+    }
+}
+
+/// Counted reference to a ChucK float[]
+pub type CkFloatArray = CkRef<chuck::Array8>;
+
+impl CkRef<chuck::Array8> {
+    pub fn len(&self) -> usize {
+        unsafe { chugin_array8_size(self.as_ptr()) as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// None if i is out of bounds
+    pub fn get(&self, i: usize) -> Option<chuck::Float> {
+        let mut val = 0.0;
+
+        match unsafe { chugin_array8_get(self.as_ptr(), i as chuck::Int, &mut val) } {
+            0 => None,
+            _ => Some(val),
+        }
+    }
+
+    /// false if i is out of bounds
+    pub fn set(&self, i: usize, val: chuck::Float) -> bool {
+        unsafe { chugin_array8_set(self.as_ptr(), i as chuck::Int, val) != 0 }
+    }
+
+    pub fn push(&self, val: chuck::Float) {
+        unsafe { chugin_array8_push_back(self.as_ptr(), val) }
+    }
+}
+
+/// Counted reference to a ChucK Event
+pub type CkEvent = CkRef<chuck::Event>;
+
+impl CkRef<chuck::Event> {
+    /// Wake the first shred waiting on the event
+    /// Must be called from the VM thread, i.e. inside a chugin callback
+    pub fn signal(&self) {
+        unsafe { chugin_event_signal_local(self.as_ptr()) }
+    }
+
+    /// Wake every shred waiting on the event
+    /// Must be called from the VM thread, i.e. inside a chugin callback
+    pub fn broadcast(&self) {
+        unsafe { chugin_event_broadcast_local(self.as_ptr()) }
+    }
+
+    /// Get a handle that can broadcast the event from other threads; ctx is
+    /// that of the callback running on the VM thread
+    /// Unsafe because the handle does not keep the event alive: this CkEvent
+    /// (or a clone) must outlive every use of the handle
+    pub unsafe fn global(&self, ctx: &Context) -> CKResult<GlobalEvent> {
+        Ok(GlobalEvent {
+            ptr: self.ptr,
+            buffer: EventBuffer::of(ctx.vm())?,
+        })
+    }
+}
+
+/// A VM's buffer of broadcasts queued from other threads, which ChucK drains
+/// on the VM thread, waking the shreds there
+/// CBufferSimple takes one writer at a time, so the handles of a VM take
+/// turns through the lock; the VM thread never waits on it
+struct EventBuffer {
+    buffer: Mutex<NonNull<c_void>>,
+}
+
+// the buffer is only written under the lock
+unsafe impl Send for EventBuffer {}
+unsafe impl Sync for EventBuffer {}
+
+impl EventBuffer {
+    /// The VM's buffer, created the first time a handle is made for it
+    /// Must be called on the VM thread, which ChucK expects buffers to be
+    /// created from
+    fn of(vm: Vm) -> CKResult<Arc<EventBuffer>> {
+        if vm.as_ptr().is_null() {
+            return Err(Error::NullPointer("vm"));
+        }
+
+        if let Some(buffer) = vm.state::<EventBuffer>() {
+            return Ok(buffer);
+        }
+
+        match NonNull::new(unsafe { chugin_vm_create_event_buffer(vm.as_ptr()) }) {
+            Some(buffer) => Ok(vm.set_state(EventBuffer {
+                buffer: Mutex::new(buffer),
+            })),
+            None => Err(Error::NullPointer("event buffer")),
+        }
+    }
+}
+
+/// Handle for broadcasting a ChucK Event from outside the VM thread
+/// Created with CkEvent::global or GlobalEvent::from_object
+/// ChucK can only queue broadcasts, so there is no cross-thread signal
+#[derive(Clone)]
+pub struct GlobalEvent {
+    ptr: NonNull<chuck::Event>,
+    buffer: Arc<EventBuffer>,
+}
+
+// broadcast only queues the event on the VM's buffer, under its lock, and
+// the VM wakes the waiting shreds on its own thread
+unsafe impl Send for GlobalEvent {}
+unsafe impl Sync for GlobalEvent {}
+
+impl GlobalEvent {
+    /// Get a handle for an object whose class extends Event, e.g. the object
+    /// a callback was invoked on; ctx is that callback's
+    /// Unsafe because obj must be an Event that outlives every use of the
+    /// handle
+    pub unsafe fn from_object(obj: *mut chuck::Object, ctx: &Context) -> CKResult<GlobalEvent> {
+        match NonNull::new(obj as *mut chuck::Event) {
+            Some(ptr) => Ok(GlobalEvent {
+                ptr,
+                buffer: EventBuffer::of(ctx.vm())?,
+            }),
+            None => Err(Error::NullPointer("event")),
+        }
+    }
+
+    /// Wake every shred waiting on the event, once the VM next runs
+    pub fn broadcast(&self) {
+        let buffer = self.buffer.buffer.lock().unwrap_or_else(|e| e.into_inner());
+
+        unsafe { chugin_event_queue_broadcast(self.ptr.as_ptr(), buffer.as_ptr()) }
+    }
+}
@@ -2,11 +2,16 @@ use crate::chuck;
 
 /// trait for working with ChucK types (int, float, etc.)
 pub trait CKType {
+    /// name of the corresponding type in ChucK
+    const TYPE_NAME: &'static str;
+
     fn get_next_arg(args: chuck::Args) -> (chuck::Args, Self);
 }
 
 /// CKType impl for ChucK float (f64)
 impl CKType for chuck::Float {
+    const TYPE_NAME: &'static str = "float";
+
     fn get_next_arg(args: chuck::Args) -> (chuck::Args, Self) {
         // convert to array of arg type
         let args = args as *const chuck::Float;
@@ -18,3 +23,26 @@ impl CKType for chuck::Float {
         (args, arg)
     }
 }
+
+/// CKType impl for ChucK int
+impl CKType for chuck::Int {
+    const TYPE_NAME: &'static str = "int";
+
+    fn get_next_arg(args: chuck::Args) -> (chuck::Args, Self) {
+        let args = args as *const chuck::Int;
+        let arg = unsafe { *args };
+        let args = unsafe { args.offset(1) as chuck::Args };
+        (args, arg)
+    }
+}
+
+/// ChucK float arguments narrowed to f32, for DSP code working in single
+/// precision
+impl CKType for f32 {
+    const TYPE_NAME: &'static str = "float";
+
+    fn get_next_arg(args: chuck::Args) -> (chuck::Args, Self) {
+        let (args, arg) = chuck::Float::get_next_arg(args);
+        (args, arg as f32)
+    }
+}
@@ -0,0 +1,288 @@
+// Typestate builder for registering classes with ChucK
+
+use crate::args::{ArgList, ArgSpec, Mfun};
+use crate::chuck;
+use crate::data_offset::DataOffset;
+use crate::member::{MemberType, MemberVar};
+use crate::method::{CKReturn, ChuginObject, Method};
+use crate::overload::Overloads;
+use crate::param::ParamInfo;
+#[cfg(feature = "serde")]
+use crate::preset::{self, PresetParam};
+use crate::query::Query;
+#[cfg(feature = "serde")]
+use crate::Error;
+use crate::CKResult;
+use std::marker::PhantomData;
+
+/// Marker for classes that extend a plain Object
+pub struct ObjectKind;
+
+/// Marker for classes that extend UGen (or a UGen subclass)
+pub struct UGenKind;
+
+/// A class that has been named but whose parent is not yet known
+/// Nothing is registered with ChucK until the parent is given
+pub struct ClassDecl<'q> {
+    query: &'q Query,
+    name: String,
+}
+
+impl<'q> ClassDecl<'q> {
+    pub(crate) fn new(query: &'q Query, name: &str) -> ClassDecl<'q> {
+        ClassDecl {
+            query,
+            name: name.to_string(),
+        }
+    }
+
+    /// Extend a non-UGen parent class, e.g. "Object"; UGen parents known to
+    /// the query are rejected in favour of extends_ugen
+    pub fn extends(self, parent: &str) -> ClassBuilder<'q, ObjectKind> {
+        ClassBuilder::begin(self.query, self.query.begin_object_class(&self.name, parent))
+    }
+
+    /// Extend "UGen", "UGen_Multi", "UGen_Stereo", "UAna" or a class this
+    /// chugin has already registered with extends_ugen, enabling the
+    /// UGen-only methods
+    pub fn extends_ugen(self, parent: &str) -> ClassBuilder<'q, UGenKind> {
+        ClassBuilder::begin(self.query, self.query.begin_ugen_class(&self.name, parent))
+    }
+}
+
+/// A class that is currently being constructed
+/// Errors are deferred until finish(); if the builder is dropped without
+/// calling finish(), the class is still ended
+pub struct ClassBuilder<'q, K> {
+    query: &'q Query,
+    result: CKResult,
+    /// true between a successful begin_class and the matching end_class
+    open: bool,
+    /// parameters added so far, for presets()
+    #[cfg(feature = "serde")]
+    params: Vec<PresetParam>,
+    kind: PhantomData<K>,
+}
+
+impl<'q, K> ClassBuilder<'q, K> {
+    /// Wrap the result of starting the class with Query::begin_class
+    fn begin(query: &'q Query, result: CKResult) -> ClassBuilder<'q, K> {
+        let open = result.is_ok();
+
+        ClassBuilder {
+            query,
+            result,
+            open,
+            #[cfg(feature = "serde")]
+            params: Vec::new(),
+            kind: PhantomData,
+        }
+    }
+
+    /// Run f if no error has occurred so far, recording its error otherwise
+    fn then<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&Query) -> CKResult,
+    {
+        if self.result.is_ok() {
+            self.result = f(self.query);
+        }
+
+        self
+    }
+
+    /// Add a constructor
+    pub fn ctor(self, ctor: chuck::f_ctor) -> Self {
+        self.then(|q| q.add_ctor(ctor))
+    }
+
+    /// Add a destructor
+    pub fn dtor(self, dtor: chuck::f_dtor) -> Self {
+        self.then(|q| q.add_dtor(dtor))
+    }
+
+    /// Add a member variable; `offset` receives its offset in the object's
+    /// data segment
+    pub fn mvar<F>(self, type_: &str, name: &str, is_const: bool, offset: F) -> Self
+    where
+        F: FnOnce(usize),
+    {
+        self.then(|q| {
+            offset(q.add_mvar(type_, name, is_const)? as usize);
+            Ok(())
+        })
+    }
+
+    /// Add the `@data` member variable that holds the Rust object
+    pub fn data(self, offset: &DataOffset) -> Self {
+        self.then(|q| q.add_data_mvar(offset))
+    }
+
+    /// Add a typed member variable
+    pub fn member<T: MemberType>(self, var: &MemberVar<T>) -> Self {
+        self.then(|q| q.add_typed_mvar(var))
+    }
+
+    /// Add a member function
+    pub fn mfun(
+        self,
+        mfun: chuck::f_mfun,
+        type_: &str,
+        name: &str,
+        args: &[(String, String)],
+    ) -> Self {
+        self.then(|q| q.add_mfun(mfun, type_, name, args))
+    }
+
+    /// Add a member function with checked argument types
+    pub fn typed_mfun<A: ArgList>(
+        self,
+        mfun: Mfun<A>,
+        type_: &str,
+        name: &str,
+        args: ArgSpec<A>,
+    ) -> Self {
+        self.then(|q| q.add_typed_mfun(mfun, type_, name, args))
+    }
+
+    /// Add a member function implemented by a plain Rust function or
+    /// closure without captures; see Query::add_method
+    pub fn method<T, A, R, F>(self, name: &str, f: F) -> Self
+    where
+        T: ChuginObject,
+        A: ArgList,
+        R: CKReturn,
+        F: Method<T, A, R>,
+    {
+        self.then(|q| q.add_method(name, f))
+    }
+
+    /// Add every signature of an overload set under one name
+    pub fn overloads(self, overloads: Overloads) -> Self {
+        self.then(|q| q.add_overloads(&overloads))
+    }
+
+    /// Add `float name(float)` and `float name()` from a setter and getter
+    pub fn setter_getter(self, name: &str, setter: chuck::f_mfun, getter: chuck::f_mfun) -> Self {
+        self.then(|q| q.add_setter_getter(name, setter, getter))
+    }
+
+    /// Add the setter and getter of a float parameter, documenting its range
+    pub fn param(self, setter: chuck::f_mfun, getter: chuck::f_mfun, info: &ParamInfo) -> Self {
+        let doc = info.doc();
+
+        self.record_param(info.name, setter, getter).then(|q| {
+            q.add_mfun(
+                setter,
+                "float",
+                info.name,
+                &[(String::from("float"), String::from("value"))],
+            )?;
+            q.doc_func(&format!("Set {}; {}", info.name, doc))?;
+            q.add_mfun(getter, "float", info.name, &[])?;
+            q.doc_func(&format!("Get {}; {}", info.name, doc))
+        })
+    }
+
+    /// Remember a parameter for presets()
+    #[cfg(feature = "serde")]
+    fn record_param(mut self, name: &'static str, setter: chuck::f_mfun, getter: chuck::f_mfun) -> Self {
+        self.params.push(PresetParam {
+            name,
+            setter,
+            getter,
+        });
+        self
+    }
+
+    #[cfg(not(feature = "serde"))]
+    fn record_param(self, _name: &'static str, _setter: chuck::f_mfun, _getter: chuck::f_mfun) -> Self {
+        self
+    }
+
+    /// Add savePreset(string path), loadPreset(string path) and
+    /// presetString() for the parameters added so far
+    #[cfg(feature = "serde")]
+    pub fn presets(self) -> Self {
+        let params = self.params.clone();
+
+        self.then(|q| {
+            match q.current_class() {
+                Some(class) => preset::register(&class, params),
+                None => return Err(Error::from("presets added outside of a class")),
+            }
+
+            q.add_mfun(Some(preset::preset_string), "string", "presetString", &[])?;
+            q.doc_func("Get all parameters as a JSON preset")?;
+            q.add_mfun(
+                Some(preset::save_preset),
+                "int",
+                "savePreset",
+                &[(String::from("string"), String::from("path"))],
+            )?;
+            q.doc_func("Save all parameters to a JSON preset file; returns 1 on success")?;
+            q.add_mfun(
+                Some(preset::load_preset),
+                "int",
+                "loadPreset",
+                &[(String::from("string"), String::from("path"))],
+            )?;
+            q.doc_func("Restore parameters from a JSON preset file; returns 1 on success")
+        })
+    }
+
+    /// Add a class nested inside this one; the nested class is always ended
+    /// before this one continues
+    pub fn class<F>(self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(ClassDecl) -> CKResult,
+    {
+        self.then(|q| f(ClassDecl::new(q, name)))
+    }
+
+    /// End the class, reporting the first error that occurred while
+    /// building it
+    pub fn finish(mut self) -> CKResult {
+        let result = std::mem::replace(&mut self.result, Ok(()));
+
+        let ended = if self.open {
+            self.open = false;
+            self.query.end_class()
+        } else {
+            Ok(())
+        };
+
+        result.and(ended)
+    }
+}
+
+impl<'q> ClassBuilder<'q, UGenKind> {
+    /// Add a tick function
+    pub fn ugen(self, tick: chuck::f_tick, num_in: u32, num_out: u32) -> Self {
+        self.then(|q| q.add_ugen_func(tick, None, num_in, num_out))
+    }
+
+    /// Add a tick function with a pmsg handler
+    pub fn ugen_with_pmsg(
+        self,
+        tick: chuck::f_tick,
+        pmsg: chuck::f_pmsg,
+        num_in: u32,
+        num_out: u32,
+    ) -> Self {
+        self.then(|q| q.add_ugen_func(tick, pmsg, num_in, num_out))
+    }
+
+    /// Add a multi-channel tick function
+    pub fn ugenf(self, tickf: chuck::f_tickf, num_in: u32, num_out: u32) -> Self {
+        self.then(|q| q.add_ugen_funcf(tickf, None, num_in, num_out))
+    }
+}
+
+impl<'q, K> Drop for ClassBuilder<'q, K> {
+    fn drop(&mut self) {
+        if self.open {
+            let _ = self.query.end_class();
+        }
+    }
+}
@@ -0,0 +1,179 @@
+// Handles to the VM, shred and Api passed to chugin callbacks
+
+use crate::api::Api;
+use crate::chuck;
+use crate::method::CKReturn;
+use crate::state;
+use crate::{CKResult, Error};
+use std::any::Any;
+use std::sync::Arc;
+
+// the VM's and shreds' members aren't in the DL headers, so these go through
+// the C++ wrappers in include/vm_shim.cpp
+extern "C" {
+    fn chugin_vm_now(vm: *mut chuck::VM) -> chuck::Time;
+    fn chugin_shred_id(shred: *mut chuck::VM_Shred) -> chuck::Int;
+}
+
+/// Handle to the ChucK VM running a callback
+#[derive(Clone, Copy)]
+pub struct Vm {
+    vm: *mut chuck::VM,
+}
+
+impl Vm {
+    /// Create new wrapper from ChucK type
+    pub fn new(vm: *mut chuck::VM) -> Vm {
+        Vm { vm: vm }
+    }
+
+    /// Underlying ChucK pointer
+    pub fn as_ptr(&self) -> *mut chuck::VM {
+        self.vm
+    }
+
+    /// The VM's current logical time (`now`), in samples
+    pub fn now(&self) -> CKResult<chuck::Time> {
+        if self.vm.is_null() {
+            return Err(Error::NullPointer("vm"));
+        }
+
+        Ok(unsafe { chugin_vm_now(self.vm) })
+    }
+
+    /// State of type S stored for this VM with Query::init_state
+    /// Takes a lock, so fetch it in the ctor rather than in tick
+    pub fn state<S: Any + Send + Sync>(&self) -> Option<Arc<S>> {
+        state::get(self.vm as usize)
+    }
+
+    /// Store state of type S for this VM, replacing any previous state
+    pub fn set_state<S: Any + Send + Sync>(&self, value: S) -> Arc<S> {
+        state::insert(self.vm as usize, value)
+    }
+
+    /// Drop this VM's state of type S; objects still holding it keep it alive
+    pub fn remove_state<S: Any + Send + Sync>(&self) -> Option<Arc<S>> {
+        state::remove(self.vm as usize)
+    }
+
+    /// Sample rate of the VM, as recorded when the chugin was queried; None
+    /// if ChucK didn't report one
+    pub fn srate(&self) -> Option<chuck::UInt> {
+        match state::info(self.vm as usize) {
+            Some(info) if info.srate != 0 => Some(info.srate),
+            _ => None,
+        }
+    }
+}
+
+/// Handle to the ChucK shred running a callback
+#[derive(Clone, Copy)]
+pub struct Shred {
+    shred: *mut chuck::VM_Shred,
+}
+
+impl Shred {
+    /// Create new wrapper from ChucK type
+    pub fn new(shred: *mut chuck::VM_Shred) -> Shred {
+        Shred { shred: shred }
+    }
+
+    /// Underlying ChucK pointer
+    pub fn as_ptr(&self) -> *mut chuck::VM_Shred {
+        self.shred
+    }
+
+    /// The shred's id, as returned by `me.id()`
+    pub fn id(&self) -> CKResult<chuck::Int> {
+        if self.shred.is_null() {
+            return Err(Error::NullPointer("shred"));
+        }
+
+        Ok(unsafe { chugin_shred_id(self.shred) })
+    }
+}
+
+/// Everything ChucK passes to a ctor, dtor, mfun or pmsg callback besides
+/// the object and its arguments
+#[derive(Clone, Copy)]
+pub struct Context {
+    vm: Vm,
+    shred: Shred,
+    api: Api,
+    object: *mut chuck::Object,
+}
+
+impl Context {
+    pub fn new(vm: *mut chuck::VM, shred: *mut chuck::VM_Shred, api: chuck::CK_DL_API) -> Context {
+        crate::log::set_current_vm(Vm::new(vm));
+
+        Context {
+            vm: Vm::new(vm),
+            shred: Shred::new(shred),
+            api: Api::new(api),
+            object: std::ptr::null_mut(),
+        }
+    }
+
+    /// Attach the object the callback was invoked on
+    pub fn with_object(self, object: *mut chuck::Object) -> Context {
+        Context { object, ..self }
+    }
+
+    pub fn vm(&self) -> Vm {
+        self.vm
+    }
+
+    pub fn shred(&self) -> Shred {
+        self.shred
+    }
+
+    pub fn api(&self) -> Api {
+        self.api
+    }
+
+    /// The object the callback was invoked on, e.g. for MemberVar::get;
+    /// null outside of ctors, dtors and member functions
+    pub fn object(&self) -> *mut chuck::Object {
+        self.object
+    }
+
+    /// Return a value from a callback defined with the mfun macros, e.g.
+    /// `chugin::This` to allow chaining
+    /// Unsafe because return_ must be the callback's return slot
+    pub unsafe fn set_return<R: CKReturn>(&self, return_: *mut chuck::Chuck_DL_Return, value: R) {
+        value.encode(self.object, return_);
+    }
+
+    /// Current logical time (`now`), in samples
+    pub fn now(&self) -> CKResult<chuck::Time> {
+        self.vm.now()
+    }
+
+    /// Sample rate of the VM
+    /// Callbacks ChucK invokes without a shred get the rate recorded for the
+    /// VM when the chugin was queried
+    pub fn srate(&self) -> CKResult<chuck::UInt> {
+        if !self.shred.as_ptr().is_null() {
+            return self.api.srate(self.shred);
+        }
+
+        self.vm.srate().ok_or(Error::NullPointer("shred"))
+    }
+
+    /// Instantiate a new ChucK object of the given type on the current shred
+    pub fn create(&self, type_: *mut chuck::Type) -> CKResult<*mut chuck::Object> {
+        self.api.create(self.shred, type_)
+    }
+
+    /// Look up a ChucK type by name
+    pub fn get_type(&self, name: &str) -> CKResult<*mut chuck::Type> {
+        self.api.get_type(self.shred, name)
+    }
+
+    /// Instantiate a new ChucK string on the current shred
+    pub fn create_string(&self, value: &str) -> CKResult<*mut chuck::String> {
+        self.api.create_string(self.shred, value)
+    }
+}
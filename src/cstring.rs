@@ -1,4 +1,4 @@
-use crate::CKResult;
+use crate::{CKResult, Error};
 use std::ffi;
 
 /// Wrapper to encapsulate ffi::CString + easily convert to byte representation
@@ -10,10 +10,16 @@ pub struct CString {
 }
 
 impl CString {
-    pub fn new(s: &str) -> CKResult<CString> {
+    /// `which` describes the string for error reporting, e.g. "class name"
+    pub fn new(which: &'static str, s: &str) -> CKResult<CString> {
         let s = match ffi::CString::new(s) {
             Ok(s) => s,
-            Err(_) => return Err("unable to convert C-string: name"),
+            Err(_) => {
+                return Err(Error::InteriorNul {
+                    which,
+                    value: s.to_string(),
+                })
+            }
         };
 
         return Ok(CString { cstring: s });
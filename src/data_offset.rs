@@ -0,0 +1,75 @@
+// Where a class keeps its Rust object in the ChucK object's data segment
+
+use crate::{CKResult, Error};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const UNSET: usize = usize::MAX;
+
+/// Set-once cell holding the offset of a class's `@data` member variable
+/// Declare one per class as a plain `static`, fill it with
+/// ClassBuilder::data (or Query::add_data_mvar) and pass it to the
+/// ctor/dtor/mfun/tick macros in place of a `static mut usize`
+pub struct DataOffset {
+    offset: AtomicUsize,
+}
+
+impl DataOffset {
+    pub const fn new() -> DataOffset {
+        DataOffset {
+            offset: AtomicUsize::new(UNSET),
+        }
+    }
+
+    /// Record the offset; ChucK reports the same offset to every VM that
+    /// loads the chugin, so setting it again to the same value is fine
+    pub fn set(&self, offset: usize) -> CKResult {
+        match self
+            .offset
+            .compare_exchange(UNSET, offset, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => Ok(()),
+            Err(set) if set == offset => Ok(()),
+            Err(set) => Err(Error::DataOffsetMismatch { set, new: offset }),
+        }
+    }
+
+    /// The offset, or None if it hasn't been registered yet
+    pub fn try_get(&self) -> Option<usize> {
+        match self.offset.load(Ordering::Acquire) {
+            UNSET => None,
+            offset => Some(offset),
+        }
+    }
+
+    /// The offset
+    /// Panics if the `@data` member variable hasn't been registered
+    pub fn get(&self) -> usize {
+        match self.try_get() {
+            Some(offset) => offset,
+            None => panic!("DataOffset used before its @data member variable was registered"),
+        }
+    }
+}
+
+impl Default for DataOffset {
+    fn default() -> DataOffset {
+        DataOffset::new()
+    }
+}
+
+/// Anything the macros accept as an object data offset
+pub trait Offset {
+    fn offset(&self) -> usize;
+}
+
+impl Offset for usize {
+    fn offset(&self) -> usize {
+        *self
+    }
+}
+
+impl Offset for DataOffset {
+    fn offset(&self) -> usize {
+        self.get()
+    }
+}
@@ -0,0 +1,193 @@
+// Registering dspz generators and processors as UGens
+//
+// Any type implementing dspz's Generator or Processor can be registered,
+// given the function creating it at a sample rate, e.g.
+// `chugin::dspz::register_processor(&q, "RustKorg35", Korg35::new)`
+
+use crate::chuck;
+use crate::class::{ClassBuilder, UGenKind};
+use crate::context::Context;
+use crate::data_offset::DataOffset;
+use crate::method::AssertNoCaptures;
+use crate::query::Query;
+use crate::util;
+use crate::{CKResult, DataError};
+use ::dspz::traits::{Generator, Periodic, Processor};
+use ::dspz::types::Float;
+use std::os::raw::c_void;
+
+/// Every adapter class extends UGen directly, so they all keep their object
+/// at the same offset
+static DATA_OFFSET: DataOffset = DataOffset::new();
+
+extern "C" fn ctor<T: 'static, F: Fn(Float) -> T + Copy + 'static>(
+    ck_self: *mut chuck::Object,
+    _args: *mut c_void,
+    vm: *mut chuck::VM,
+    shred: *mut chuck::VM_Shred,
+    api: chuck::CK_DL_API,
+) {
+    let () = AssertNoCaptures::<F>::OK;
+
+    let ctx = Context::new(vm, shred, api);
+    let srate = ctx.srate().unwrap_or(44100) as Float;
+
+    // F is zero-sized, so this is the constructor that was registered
+    let new: F = unsafe { std::mem::zeroed() };
+
+    unsafe { util::set_object_data(ck_self, DATA_OFFSET.get(), Box::new(new(srate))) };
+}
+
+extern "C" fn dtor<T: 'static>(
+    ck_self: *mut chuck::Object,
+    _vm: *mut chuck::VM,
+    _shred: *mut chuck::VM_Shred,
+    _api: chuck::CK_DL_API,
+) {
+    drop(unsafe { util::get_object_data::<T>(ck_self, DATA_OFFSET.get()) });
+}
+
+extern "C" fn tick_generator<T: Generator + 'static>(
+    ck_self: *mut chuck::Object,
+    _inp: f32,
+    out: *mut f32,
+    _api: chuck::CK_DL_API,
+) -> chuck::t_CKBOOL {
+    let sample =
+        unsafe { util::try_with_object_data(ck_self, DATA_OFFSET.get(), |obj: &mut T| obj.tick()) };
+
+    unsafe { *out = sample.unwrap_or(0.0) as f32 };
+
+    chuck::CK_TRUE
+}
+
+extern "C" fn tick_processor<T: Processor + 'static>(
+    ck_self: *mut chuck::Object,
+    inp: f32,
+    out: *mut f32,
+    _api: chuck::CK_DL_API,
+) -> chuck::t_CKBOOL {
+    let sample = unsafe {
+        util::try_with_object_data(ck_self, DATA_OFFSET.get(), |obj: &mut T| {
+            obj.tick(inp as Float)
+        })
+    };
+
+    unsafe { *out = sample.unwrap_or(0.0) as f32 };
+
+    chuck::CK_TRUE
+}
+
+extern "C" fn set_freq<T: Periodic + 'static>(
+    ck_self: *mut chuck::Object,
+    args: *mut c_void,
+    return_: *mut chuck::Chuck_DL_Return,
+    _vm: *mut chuck::VM,
+    _shred: *mut chuck::VM_Shred,
+    _api: chuck::CK_DL_API,
+) {
+    let (_, freq) = util::get_next_arg::<chuck::Float>(args as chuck::Args);
+
+    let freq = unsafe {
+        util::try_with_object_data(ck_self, DATA_OFFSET.get(), |obj: &mut T| {
+            obj.set_freq(freq as Float);
+            obj.get_freq()
+        })
+    };
+
+    unsafe { (*return_).v_float = freq_or_zero(freq) };
+}
+
+extern "C" fn get_freq<T: Periodic + 'static>(
+    ck_self: *mut chuck::Object,
+    _args: *mut c_void,
+    return_: *mut chuck::Chuck_DL_Return,
+    _vm: *mut chuck::VM,
+    _shred: *mut chuck::VM_Shred,
+    _api: chuck::CK_DL_API,
+) {
+    let freq = unsafe {
+        util::try_with_object_data(ck_self, DATA_OFFSET.get(), |obj: &mut T| obj.get_freq())
+    };
+
+    unsafe { (*return_).v_float = freq_or_zero(freq) };
+}
+
+/// The frequency a freq method returns, or 0 if the object couldn't be
+/// borrowed
+fn freq_or_zero(freq: Result<Float, DataError>) -> chuck::Float {
+    match freq {
+        Ok(freq) => freq,
+        Err(e) => {
+            crate::log::eprint(&format!("[chugin]: freq called, but the {}\n", e));
+            0.0
+        }
+    }
+}
+
+fn ugen<T: 'static, F: Fn(Float) -> T + Copy + 'static>(
+    class: ClassBuilder<'_, UGenKind>,
+    _new: F,
+) -> ClassBuilder<'_, UGenKind> {
+    let () = AssertNoCaptures::<F>::OK;
+
+    class
+        .ctor(Some(ctor::<T, F>))
+        .dtor(Some(dtor::<T>))
+        .data(&DATA_OFFSET)
+}
+
+/// Start a UGen class with no inputs and one output driven by the Generator
+/// `new` creates; finish() it after adding any further methods
+pub fn generator<'q, T, F>(q: &'q Query, name: &str, new: F) -> ClassBuilder<'q, UGenKind>
+where
+    T: Generator + 'static,
+    F: Fn(Float) -> T + Copy + 'static,
+{
+    ugen(q.class(name).extends_ugen("UGen"), new).ugen(Some(tick_generator::<T>), 0, 1)
+}
+
+/// Like generator, also adding `float freq(float)` and `float freq()` for a
+/// Periodic generator
+pub fn periodic_generator<'q, T, F>(q: &'q Query, name: &str, new: F) -> ClassBuilder<'q, UGenKind>
+where
+    T: Generator + Periodic + 'static,
+    F: Fn(Float) -> T + Copy + 'static,
+{
+    generator(q, name, new)
+        .mfun(
+            Some(set_freq::<T>),
+            "float",
+            "freq",
+            &[(String::from("float"), String::from("f"))],
+        )
+        .mfun(Some(get_freq::<T>), "float", "freq", &[])
+}
+
+/// Start a mono-in, mono-out UGen class driven by the Processor `new`
+/// creates; finish() it after adding any further methods
+pub fn processor<'q, T, F>(q: &'q Query, name: &str, new: F) -> ClassBuilder<'q, UGenKind>
+where
+    T: Processor + 'static,
+    F: Fn(Float) -> T + Copy + 'static,
+{
+    ugen(q.class(name).extends_ugen("UGen"), new).ugen(Some(tick_processor::<T>), 1, 1)
+}
+
+/// Register a Generator as a complete UGen class
+pub fn register_generator<T, F>(q: &Query, name: &str, new: F) -> CKResult
+where
+    T: Generator + 'static,
+    F: Fn(Float) -> T + Copy + 'static,
+{
+    generator(q, name, new).finish()
+}
+
+/// Register a Processor as a complete UGen class
+pub fn register_processor<T, F>(q: &Query, name: &str, new: F) -> CKResult
+where
+    T: Processor + 'static,
+    F: Fn(Float) -> T + Copy + 'static,
+{
+    processor(q, name, new).finish()
+}
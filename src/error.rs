@@ -0,0 +1,112 @@
+use std::error;
+use std::fmt;
+
+/// Chugin error type
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A pointer handed to us by ChucK was null
+    NullPointer(&'static str),
+    /// A string could not be passed to ChucK because it contains a nul byte
+    InteriorNul { which: &'static str, value: String },
+    /// The query object is missing one of its function pointers
+    MissingQueryFn(&'static str),
+    /// The DL Api is missing one of its function pointers
+    MissingApiFn(&'static str),
+    /// A DL Api call reported failure
+    ApiCallFailed(&'static str),
+    /// A query call reported failure
+    QueryCallFailed(&'static str),
+    /// A class method was called while no class was being constructed
+    NoCurrentClass,
+    /// ChucK failed to finish a class
+    EndClassFailed { class: String },
+    /// extends_ugen was given a parent that isn't a known UGen class
+    NotAUGen { class: String, parent: String },
+    /// extends was given a UGen parent, which needs extends_ugen
+    UGenParent { class: String, parent: String },
+    /// A member function signature was added to a class twice
+    DuplicateSignature { class: String, signature: String },
+    /// A DataOffset was registered twice with different offsets
+    DataOffsetMismatch { set: usize, new: usize },
+    /// ChucK failed to create, activate or deactivate a main thread hook
+    MainThreadHookFailed(&'static str),
+    /// Error raised by chugin code
+    Custom(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NullPointer(what) => write!(f, "invalid {} object (null pointer)", what),
+            Error::InteriorNul { which, value } => {
+                write!(f, "unable to convert {} to C-string: {:?}", which, value)
+            }
+            Error::MissingQueryFn(name) => {
+                write!(f, "invalid query object (missing function '{}')", name)
+            }
+            Error::MissingApiFn(name) => {
+                write!(f, "invalid api object (missing function '{}')", name)
+            }
+            Error::ApiCallFailed(name) => write!(f, "api call '{}' failed", name),
+            Error::QueryCallFailed(name) => write!(f, "query call '{}' failed", name),
+            Error::NoCurrentClass => write!(f, "no class is currently being constructed"),
+            Error::EndClassFailed { class } => write!(f, "failed to end class '{}'", class),
+            Error::NotAUGen { class, parent } => {
+                write!(f, "class '{}' extends '{}', which is not a UGen class", class, parent)
+            }
+            Error::UGenParent { class, parent } => write!(
+                f,
+                "class '{}' extends the UGen class '{}'; declare it with extends_ugen",
+                class, parent
+            ),
+            Error::DuplicateSignature { class, signature } => {
+                write!(f, "{}.{} is already defined", class, signature)
+            }
+            Error::DataOffsetMismatch { set, new } => write!(
+                f,
+                "data offset already set to {} (attempted to set {})",
+                set, new
+            ),
+            Error::MainThreadHookFailed(action) => {
+                write!(f, "failed to {} main thread hook", action)
+            }
+            Error::Custom(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Why an object's Rust data couldn't be borrowed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataError {
+    /// The object has no data of the expected type, e.g. an object of a
+    /// Rust base class itself, or of a ChucK class extending it
+    Missing,
+    /// The data is already borrowed, e.g. by a method running on the same
+    /// object
+    Borrowed,
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataError::Missing => write!(f, "object has no data"),
+            DataError::Borrowed => write!(f, "object data is already in use"),
+        }
+    }
+}
+
+impl error::Error for DataError {}
+
+impl From<&str> for Error {
+    fn from(msg: &str) -> Error {
+        Error::Custom(msg.to_string())
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Error {
+        Error::Custom(msg)
+    }
+}
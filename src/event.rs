@@ -0,0 +1,56 @@
+// Queues for handing data from other threads to Event subclasses
+
+use crate::ckref::GlobalEvent;
+use std::sync::mpsc;
+
+/// Create a queue whose sender broadcasts `event` every time it sends
+/// Typically `event` is the object itself, for a class that extends Event;
+/// the sending half can live on any thread, the receiving half is read from
+/// the VM thread, e.g. in a recv() member function
+pub fn channel<T>(event: GlobalEvent) -> (EventSender<T>, EventReceiver<T>) {
+    let (tx, rx) = mpsc::channel();
+
+    (EventSender { tx, event }, EventReceiver { rx })
+}
+
+/// Sending half of an event queue
+pub struct EventSender<T> {
+    tx: mpsc::Sender<T>,
+    event: GlobalEvent,
+}
+
+impl<T> EventSender<T> {
+    /// Queue a value and wake every shred waiting on the event, once the VM
+    /// next runs
+    /// Returns false if the receiving half has been dropped
+    pub fn send(&self, value: T) -> bool {
+        if self.tx.send(value).is_err() {
+            return false;
+        }
+
+        self.event.broadcast();
+
+        true
+    }
+}
+
+impl<T> Clone for EventSender<T> {
+    fn clone(&self) -> Self {
+        EventSender {
+            tx: self.tx.clone(),
+            event: self.event.clone(),
+        }
+    }
+}
+
+/// Receiving half of an event queue
+pub struct EventReceiver<T> {
+    rx: mpsc::Receiver<T>,
+}
+
+impl<T> EventReceiver<T> {
+    /// Take the oldest queued value, if any; never blocks
+    pub fn recv(&self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+}
@@ -10,70 +10,186 @@ macro_rules! query {
         pub extern "C" fn ck_query($ck_query: *mut chuck::DL_Query) -> chuck::t_CKBOOL {
             match $query {
                 Ok(_) => chuck::CK_TRUE,
-                Err(_) => chuck::CK_FALSE,
+                Err(e) => {
+                    chugin::ck_eprintln!("[{}]: {}", env!("CARGO_PKG_NAME"), e);
+                    chuck::CK_FALSE
+                }
             }
         }
     };
 }
 
+/// Define a constructor; pass an identifier before the object expression to
+/// bind a chugin::Context for use while constructing it
 #[macro_export]
 macro_rules! ctor {
+    ($ident:ident, $offset:expr, $ctx:ident, $obj:expr) => {
+        #[no_mangle]
+        pub extern "C" fn $ident(
+            ck_self: *mut chuck::Object,
+            _args: *mut ::std::os::raw::c_void,
+            vm: *mut chuck::VM,
+            shred: *mut chuck::VM_Shred,
+            api: chuck::CK_DL_API,
+        ) {
+            let $ctx = chugin::Context::new(vm, shred, api).with_object(ck_self);
+
+            let obj = Box::new($obj);
+
+            unsafe {
+                chugin::util::set_object_data(ck_self, chugin::data_offset::Offset::offset(&$offset), obj);
+            }
+        }
+    };
     ($ident:ident, $offset:expr, $obj:expr) => {
+        chugin::ctor!($ident, $offset, _ctx, $obj);
+    };
+}
+
+/// Define a constructor for a class that extends Event; `$event` is bound to
+/// a chugin::GlobalEvent for the object being constructed, e.g. for use with
+/// chugin::event::channel
+/// The object data must stop using the event (e.g. join any threads holding
+/// it) when it is dropped in the dtor
+#[macro_export]
+macro_rules! event_ctor {
+    ($ident:ident, $offset:expr, $ctx:ident, $event:ident, $obj:expr) => {
         #[no_mangle]
         pub extern "C" fn $ident(
             ck_self: *mut chuck::Object,
             _args: *mut ::std::os::raw::c_void,
-            _vm: *mut chuck::VM,
-            _shred: *mut chuck::VM_Shred,
-            _api: chuck::CK_DL_API,
+            vm: *mut chuck::VM,
+            shred: *mut chuck::VM_Shred,
+            api: chuck::CK_DL_API,
         ) {
+            let $ctx = chugin::Context::new(vm, shred, api).with_object(ck_self);
+
+            let $event = match unsafe { chugin::GlobalEvent::from_object(ck_self, &$ctx) } {
+                Ok(event) => event,
+                Err(e) => {
+                    chugin::log::eprint(&format!(concat!("[chugin]: ", stringify!($ident), ": {}\n"), e));
+                    return;
+                }
+            };
+
             let obj = Box::new($obj);
 
             unsafe {
-                chugin::util::set_object_data(ck_self, $offset, obj);
+                chugin::util::set_object_data(ck_self, chugin::data_offset::Offset::offset(&$offset), obj);
             }
         }
     };
+    ($ident:ident, $offset:expr, $event:ident, $obj:expr) => {
+        chugin::event_ctor!($ident, $offset, _ctx, $event, $obj);
+    };
 }
 
 #[macro_export]
 macro_rules! dtor {
-    ($ident:ident, $offset:expr, $t:ty, $obj:ident, $code:stmt) => {
+    ($ident:ident, $offset:expr, $t:ty, $obj:ident, $ctx:ident, $code:stmt) => {
         #[no_mangle]
         pub extern "C" fn $ident(
             ck_self: *mut chuck::Object,
-            _vm: *mut chuck::VM,
-            _shred: *mut chuck::VM_Shred,
-            _api: chuck::CK_DL_API,
+            vm: *mut chuck::VM,
+            shred: *mut chuck::VM_Shred,
+            api: chuck::CK_DL_API,
         ) {
-            let $obj: Box<$t> = unsafe { chugin::util::get_object_data(ck_self, $offset) };
+            let $ctx = chugin::Context::new(vm, shred, api).with_object(ck_self);
+
+            let $obj: Box<$t> = unsafe { chugin::util::get_object_data(ck_self, chugin::data_offset::Offset::offset(&$offset)) };
 
             $code
         }
     };
+    ($ident:ident, $offset:expr, $t:ty, $obj:ident, $code:stmt) => {
+        chugin::dtor!($ident, $offset, $t, $obj, _ctx, $code);
+    };
 }
 
 #[macro_export]
 macro_rules! mfun {
-    ($ident:ident, $offset:expr, $t:ty, $obj:ident, $args:ident, $return_:ident, $code:stmt)=>{
+    ($ident:ident, $offset:expr, $t:ty, $obj:ident, $args:ident, $return_:ident, $ctx:ident, $code:stmt)=>{
         #[no_mangle]
         pub extern "C" fn $ident(
             ck_self: *mut chuck::Chuck_Object,
             $args: *mut ::std::os::raw::c_void,
             $return_: *mut chuck::Chuck_DL_Return,
-            _vm: *mut chuck::Chuck_VM,
-            _shred: *mut chuck::Chuck_VM_Shred,
-            _api: chuck::CK_DL_API) {
+            vm: *mut chuck::Chuck_VM,
+            shred: *mut chuck::Chuck_VM_Shred,
+            api: chuck::CK_DL_API) {
 
-            let mut $obj: Box<$t> = unsafe {
-                chugin::util::get_object_data(ck_self, $offset)
-            };
+            let $ctx = chugin::Context::new(vm, shred, api).with_object(ck_self);
 
-            $code
+            let f = |$obj: &mut $t| {
+                $code;
+            };
+            let done = unsafe {
+                chugin::util::try_with_object_data(ck_self, chugin::data_offset::Offset::offset(&$offset), f)
+            };
 
-            Box::into_raw($obj);
+            if let Err(e) = done {
+                chugin::log::eprint(&format!(concat!("[chugin]: ", stringify!($ident), " called, but the {}\n"), e));
+            }
         }
-    }
+    };
+    ($ident:ident, $offset:expr, $t:ty, $obj:ident, $args:ident, $return_:ident, $code:stmt)=>{
+        chugin::mfun!($ident, $offset, $t, $obj, $args, $return_, _ctx, $code);
+    };
+}
+
+/// Like mfun!, but the arguments are declared with their ChucK types and
+/// decoded automatically; `$ident` becomes a typed chugin::args::Mfun that
+/// can only be registered with a matching args! spec
+#[macro_export]
+macro_rules! mfun_typed {
+    ($ident:ident,
+     $offset:expr,
+     $t:ty,
+     $obj:ident,
+     ($($arg:ident : $type_:ident),*),
+     $return_:ident,
+     $ctx:ident,
+     $code:stmt)=>{
+        #[allow(non_upper_case_globals)]
+        const $ident: chugin::args::Mfun<($(chugin::ck_type!($type_),)*)> = {
+            extern "C" fn mfun(
+                ck_self: *mut chuck::Chuck_Object,
+                args: *mut ::std::os::raw::c_void,
+                $return_: *mut chuck::Chuck_DL_Return,
+                vm: *mut chuck::Chuck_VM,
+                shred: *mut chuck::Chuck_VM_Shred,
+                api: chuck::CK_DL_API) {
+
+                let $ctx = chugin::Context::new(vm, shred, api).with_object(ck_self);
+
+                let ($($arg,)*) = unsafe {
+                    <($(chugin::ck_type!($type_),)*) as chugin::args::ArgList>::decode(args, &$ctx)
+                };
+
+                let f = |$obj: &mut $t| {
+                    $code;
+                };
+                let done = unsafe {
+                    chugin::util::try_with_object_data(ck_self, chugin::data_offset::Offset::offset(&$offset), f)
+                };
+
+                if let Err(e) = done {
+                    chugin::log::eprint(&format!(concat!("[chugin]: ", stringify!($ident), " called, but the {}\n"), e));
+                }
+            }
+
+            chugin::args::Mfun::new(Some(mfun))
+        };
+    };
+    ($ident:ident,
+     $offset:expr,
+     $t:ty,
+     $obj:ident,
+     ($($arg:ident : $type_:ident),*),
+     $return_:ident,
+     $code:stmt)=>{
+        chugin::mfun_typed!($ident, $offset, $t, $obj, ($($arg : $type_),*), $return_, _ctx, $code);
+    };
 }
 
 #[macro_export]
@@ -82,13 +198,40 @@ macro_rules! mfun_getter_float {
         chugin::mfun!($ident, $offset, $t, $obj, args, return_, {
             let val = $code;
 
-            unsafe { *return_ }.v_float = val as f64;
+            unsafe { (*return_).v_float = val as f64 };
         });
     };
 }
 
+/// Define a float setter that returns the new value; pass a
+/// chugin::param::ParamInfo last to clamp or reject out-of-range values
 #[macro_export]
 macro_rules! mfun_setter_float {
+    ($ident:ident,
+     $offset:expr,
+     $t:ty,
+     $obj:ident,
+     $val:ident,
+     $code_set:stmt,
+     $code_get:expr,
+     $info:expr)=>{
+        chugin::mfun! ($ident, $offset, $t, $obj, args, return_, ctx, {
+
+            let (_, $val) = unsafe {
+                chugin::util::get_next_arg(args)
+            } as (chuck::Args, chuck::Float);
+
+            let srate = ctx.srate().ok().map(|srate| srate as f64);
+
+            if let Some($val) = $info.check($val, srate) {
+                $code_set
+            }
+
+            let the_val = $code_get;
+
+            unsafe { (*return_).v_float = the_val as f64 };
+        });
+    };
     ($ident:ident,
      $offset:expr,
      $t:ty,
@@ -106,13 +249,26 @@ macro_rules! mfun_setter_float {
 
             let the_val = $code_get;
 
-            unsafe { *return_ }.v_float = the_val as f64;
+            unsafe { (*return_).v_float = the_val as f64 };
         });
     }
 }
 
 #[macro_export]
 macro_rules! mfun_setter_getter_float {
+    ($ident_setter:ident,
+     $ident_getter:ident,
+     $offset:expr,
+     $t:ty,
+     $obj:ident,
+     $val:ident,
+     $code_set:stmt,
+     $code_get:expr,
+     $info:expr)=>{
+        chugin::mfun_setter_float! ($ident_setter, $offset, $t, $obj, $val, $code_set, $code_get, $info);
+
+        chugin::mfun_getter_float! ($ident_getter, $offset, $t, $obj, $code_get);
+    };
     ($ident_setter:ident,
      $ident_getter:ident,
      $offset:expr,
@@ -124,12 +280,71 @@ macro_rules! mfun_setter_getter_float {
         chugin::mfun_setter_float! ($ident_setter, $offset, $t, $obj, $val, $code_set, $code_get);
 
         chugin::mfun_getter_float! ($ident_getter, $offset, $t, $obj, $code_get);
-    }
+    };
 }
 
+/// Define a setter and getter for a chugin::param::Smoothed field of the
+/// object; the setter starts a ramp and both return the target value
+/// Pass a chugin::param::ParamInfo last to check the range
+#[macro_export]
+macro_rules! mfun_setter_getter_smoothed {
+    ($ident_setter:ident,
+     $ident_getter:ident,
+     $offset:expr,
+     $t:ty,
+     $obj:ident,
+     $field:ident,
+     $info:expr)=>{
+        chugin::mfun_setter_getter_float!(
+            $ident_setter,
+            $ident_getter,
+            $offset,
+            $t,
+            $obj,
+            val,
+            {
+                $obj.$field.set_target(chugin::param::Smoothable::from_f64(val));
+            },
+            { $obj.$field.target() },
+            $info
+        );
+    };
+    ($ident_setter:ident,
+     $ident_getter:ident,
+     $offset:expr,
+     $t:ty,
+     $obj:ident,
+     $field:ident)=>{
+        chugin::mfun_setter_getter_float!(
+            $ident_setter,
+            $ident_getter,
+            $offset,
+            $t,
+            $obj,
+            val,
+            {
+                $obj.$field.set_target(chugin::param::Smoothable::from_f64(val));
+            },
+            { $obj.$field.target() }
+        );
+    };
+}
+
+/// Define a tick function; list chugin::param::Smoothed fields after
+/// `smoothed` to advance them by one sample before `$out` is evaluated, or
+/// name `$this` to bind the *mut chuck::Object, e.g. for MemberVar::get
 #[macro_export]
 macro_rules! tick {
-    ($ident:ident, $offset:expr, $t:ty, $obj:ident, $inp:ident, $out:expr) => {
+    ($ident:ident, $offset:expr, $t:ty, $obj:ident, $inp:ident, smoothed [$($param:ident),*], $out:expr) => {
+        chugin::tick!($ident, $offset, $t, $obj, $inp, {
+            $(
+                $obj.$param.advance();
+            )*
+
+            $out
+        });
+    };
+    ($ident:ident, $offset:expr, $t:ty, $obj:ident, $inp:ident, $this:ident, $out:expr) => {
         #[no_mangle]
         extern "C" fn $ident(
             ck_self: *mut chuck::Object,
@@ -137,16 +352,53 @@ macro_rules! tick {
             out: *mut f32,
             _api: chuck::CK_DL_API,
         ) -> chuck::t_CKBOOL {
-            let mut $obj: Box<$t> = unsafe { chugin::util::get_object_data(ck_self, $offset) };
+            let $this: *mut chuck::Object = ck_self;
+            let f = |$obj: &mut $t| $out;
+            let out_ = unsafe {
+                chugin::util::try_with_object_data(ck_self, chugin::data_offset::Offset::offset(&$offset), f)
+            };
 
-            let out_ = $out;
+            // an object without data, or whose data is in use, outputs silence
             unsafe {
-                *out = out_;
+                *out = out_.unwrap_or(0.0);
             }
 
-            Box::into_raw($obj);
-
             chuck::CK_TRUE
         }
     };
+    ($ident:ident, $offset:expr, $t:ty, $obj:ident, $inp:ident, $out:expr) => {
+        chugin::tick!($ident, $offset, $t, $obj, $inp, _this, $out);
+    };
+}
+
+#[macro_export]
+macro_rules! pmsg {
+    ($ident:ident, $offset:expr, $t:ty) => {
+        #[no_mangle]
+        extern "C" fn $ident(
+            ck_self: *mut chuck::Object,
+            msg: *const ::std::os::raw::c_char,
+            _args: *mut ::std::os::raw::c_void,
+            _vm: *mut chuck::VM,
+            _shred: *mut chuck::VM_Shred,
+            _api: chuck::CK_DL_API,
+        ) -> chuck::t_CKBOOL {
+            let msg = match unsafe { chugin::util::get_str(msg) } {
+                Some(msg) => msg,
+                None => return chuck::CK_FALSE,
+            };
+
+            let handled = unsafe {
+                chugin::util::try_with_object_data(ck_self, chugin::data_offset::Offset::offset(&$offset), |obj: &mut $t| {
+                    obj.pmsg(msg)
+                })
+            };
+
+            if handled == Ok(true) {
+                chuck::CK_TRUE
+            } else {
+                chuck::CK_FALSE
+            }
+        }
+    };
 }
@@ -0,0 +1,226 @@
+// Registering fundsp graphs as UGens
+
+use crate::chuck;
+use crate::class::{ClassBuilder, UGenKind};
+use crate::context::Context;
+use crate::data_offset::DataOffset;
+use crate::query::Query;
+use crate::util;
+use crate::{CKResult, Error};
+use ::fundsp::audiounit::AudioUnit;
+use ::fundsp::shared::Shared;
+use std::os::raw::{c_ulong, c_void};
+
+/// Every graph class extends UGen directly, so they all keep their object at
+/// the same offset
+static DATA_OFFSET: DataOffset = DataOffset::new();
+
+/// A fundsp unit together with the Shared variables exposed to ChucK
+pub struct Graph {
+    unit: Box<dyn AudioUnit>,
+    shared: Vec<(&'static str, Shared)>,
+}
+
+impl Graph {
+    pub fn new(unit: impl AudioUnit + 'static) -> Graph {
+        Graph {
+            unit: Box::new(unit),
+            shared: Vec::new(),
+        }
+    }
+
+    /// Expose `shared` as `float name(float)` and `float name()`
+    pub fn shared(mut self, name: &'static str, shared: &Shared) -> Graph {
+        self.shared.push((name, shared.clone()));
+        self
+    }
+}
+
+/// A fundsp graph that can be registered as a UGen
+pub trait FundspUGen: 'static {
+    /// Build a new instance of the graph; called once at registration to
+    /// find its channels and variables, then once per ChucK object
+    fn graph() -> Graph;
+}
+
+struct Object {
+    unit: Box<dyn AudioUnit>,
+    shared: Vec<Shared>,
+}
+
+extern "C" fn ctor<T: FundspUGen>(
+    ck_self: *mut chuck::Object,
+    _args: *mut c_void,
+    vm: *mut chuck::VM,
+    shred: *mut chuck::VM_Shred,
+    api: chuck::CK_DL_API,
+) {
+    let ctx = Context::new(vm, shred, api);
+    let Graph { mut unit, shared } = T::graph();
+
+    unit.set_sample_rate(ctx.srate().unwrap_or(44100) as f64);
+    unit.reset();
+
+    let obj = Object {
+        unit,
+        shared: shared.into_iter().map(|(_, shared)| shared).collect(),
+    };
+
+    unsafe { util::set_object_data(ck_self, DATA_OFFSET.get(), Box::new(obj)) };
+}
+
+extern "C" fn dtor(
+    ck_self: *mut chuck::Object,
+    _vm: *mut chuck::VM,
+    _shred: *mut chuck::VM_Shred,
+    _api: chuck::CK_DL_API,
+) {
+    drop(unsafe { util::get_object_data::<Object>(ck_self, DATA_OFFSET.get()) });
+}
+
+/// Frames are interleaved, one sample per channel; OUT is the number of
+/// output channels, so that the frames can be silenced when the object
+/// can't be borrowed
+extern "C" fn tickf<const OUT: usize>(
+    ck_self: *mut chuck::Object,
+    in_: *mut f32,
+    out: *mut f32,
+    nframes: c_ulong,
+    _api: chuck::CK_DL_API,
+) -> chuck::t_CKBOOL {
+    let ticked = unsafe {
+        util::try_with_object_data(ck_self, DATA_OFFSET.get(), |obj: &mut Object| {
+            let num_in = obj.unit.inputs();
+
+            for frame in 0..nframes as usize {
+                let input: &[f32] = if num_in > 0 {
+                    std::slice::from_raw_parts(in_.add(frame * num_in), num_in)
+                } else {
+                    &[]
+                };
+                let output = std::slice::from_raw_parts_mut(out.add(frame * OUT), OUT);
+
+                obj.unit.tick(input, output);
+            }
+        })
+    };
+
+    if ticked.is_err() {
+        unsafe { std::slice::from_raw_parts_mut(out, nframes as usize * OUT).fill(0.0) };
+    }
+
+    chuck::CK_TRUE
+}
+
+extern "C" fn set_shared<const I: usize>(
+    ck_self: *mut chuck::Object,
+    args: *mut c_void,
+    return_: *mut chuck::Chuck_DL_Return,
+    _vm: *mut chuck::VM,
+    _shred: *mut chuck::VM_Shred,
+    _api: chuck::CK_DL_API,
+) {
+    let (_, value) = util::get_next_arg::<chuck::Float>(args as chuck::Args);
+
+    let set = unsafe {
+        util::try_with_object_data(ck_self, DATA_OFFSET.get(), |obj: &mut Object| {
+            obj.shared[I].set_value(value as f32);
+        })
+    };
+
+    unsafe { (*return_).v_float = if set.is_ok() { value } else { 0.0 } };
+}
+
+extern "C" fn get_shared<const I: usize>(
+    ck_self: *mut chuck::Object,
+    _args: *mut c_void,
+    return_: *mut chuck::Chuck_DL_Return,
+    _vm: *mut chuck::VM,
+    _shred: *mut chuck::VM_Shred,
+    _api: chuck::CK_DL_API,
+) {
+    let value = unsafe {
+        util::try_with_object_data(ck_self, DATA_OFFSET.get(), |obj: &mut Object| {
+            obj.shared[I].value()
+        })
+    };
+
+    unsafe { (*return_).v_float = value.unwrap_or(0.0) as f64 };
+}
+
+macro_rules! shared_fns {
+    ($($i:literal)*) => {
+        /// setter and getter for each Shared variable slot
+        const SHARED_FNS: &[(chuck::f_mfun, chuck::f_mfun)] =
+            &[$((Some(set_shared::<$i>), Some(get_shared::<$i>)),)*];
+    };
+}
+
+shared_fns!(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15);
+
+macro_rules! tickf_fns {
+    ($($n:literal)*) => {
+        /// tickf for each number of output channels
+        const TICKF_FNS: &[chuck::f_tickf] = &[$(Some(tickf::<$n>),)*];
+    };
+}
+
+tickf_fns!(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16);
+
+/// Start a UGen class for a fundsp graph, with one UGen channel per graph
+/// input and output and a setter/getter per Shared variable; finish() it
+/// after adding any further methods
+pub fn class<'q, T: FundspUGen>(q: &'q Query, name: &str) -> CKResult<ClassBuilder<'q, UGenKind>> {
+    let graph = T::graph();
+
+    let tickf = match TICKF_FNS.get(graph.unit.outputs()) {
+        Some(tickf) => *tickf,
+        None => {
+            return Err(Error::Custom(format!(
+                "{} has {} outputs; at most {} are supported",
+                name,
+                graph.unit.outputs(),
+                TICKF_FNS.len() - 1
+            )))
+        }
+    };
+
+    if graph.shared.len() > SHARED_FNS.len() {
+        return Err(Error::Custom(format!(
+            "{} exposes {} Shared variables; at most {} are supported",
+            name,
+            graph.shared.len(),
+            SHARED_FNS.len()
+        )));
+    }
+
+    let mut class = q
+        .class(name)
+        .extends_ugen("UGen")
+        .ctor(Some(ctor::<T>))
+        .dtor(Some(dtor))
+        .data(&DATA_OFFSET)
+        .ugenf(
+            tickf,
+            graph.unit.inputs() as u32,
+            graph.unit.outputs() as u32,
+        );
+
+    for ((name, _), (setter, getter)) in graph.shared.iter().zip(SHARED_FNS) {
+        class = class
+            .mfun(
+                *setter,
+                "float",
+                name,
+                &[(String::from("float"), String::from("value"))],
+            )
+            .mfun(*getter, "float", name, &[]);
+    }
+
+    Ok(class)
+}
+
+/// Register a fundsp graph as a complete UGen class
+pub fn register<T: FundspUGen>(q: &Query, name: &str) -> CKResult {
+    class::<T>(q, name)?.finish()
+}
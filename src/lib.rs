@@ -1,15 +1,52 @@
+pub mod api;
+pub mod args;
 pub mod chuck;
+pub mod ckref;
 pub mod cktype;
+pub mod class;
+pub mod context;
 mod cstring;
+pub mod data_offset;
+#[cfg(feature = "dspz")]
+pub mod dspz;
+pub mod error;
+pub mod event;
 pub mod fn_macros;
+#[cfg(feature = "fundsp")]
+pub mod fundsp;
+pub mod log;
+pub mod main_thread;
+pub mod member;
+pub mod method;
+pub mod object;
+pub mod overload;
+pub mod param;
+#[cfg(feature = "serde")]
+mod preset;
 pub mod query;
+mod state;
+pub mod subclass;
 pub mod util;
+pub mod worker;
 
 use std::result::Result;
 
 // re-export
+pub use api::Api;
+pub use ckref::{CkEvent, CkFloatArray, CkIntArray, CkRef, CkString, GlobalEvent};
 pub use cktype::CKType;
+pub use class::{ClassBuilder, ClassDecl};
+pub use context::{Context, Shred, Vm};
+pub use data_offset::DataOffset;
+pub use error::{DataError, Error};
+pub use main_thread::MainThreadHook;
+pub use member::MemberVar;
+pub use method::{ChuginObject, This};
+pub use object::{ChuginClass, ChuginCtor, Instance};
+pub use overload::Overloads;
 pub use query::Query;
+pub use subclass::{AsAny, ChuginBase, ChuginSubclass};
+pub use worker::Worker;
 
 // chuck version is #define-d, so not supported by bindgen
 // major version must be the same between chuck:chugin
@@ -27,4 +64,4 @@ pub fn version() -> chuck::t_CKUINT {
 }
 
 /// Chugin result type
-pub type CKResult<T = (), E = &'static str> = Result<T, E>;
+pub type CKResult<T = (), E = Error> = Result<T, E>;
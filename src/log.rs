@@ -0,0 +1,193 @@
+// Printing to ChucK's console (chout/cherr) and logging at the VM's verbosity
+
+use crate::chuck;
+use crate::context::Vm;
+use crate::cstring::CString;
+use crate::state::{self, VmInfo};
+use std::cell::Cell;
+use std::os::raw::{c_char, c_void};
+
+extern "C" {
+    fn chugin_query_chout(query: *mut chuck::DL_Query) -> *mut c_void;
+    fn chugin_query_cherr(query: *mut chuck::DL_Query) -> *mut c_void;
+    fn chugin_io_write(io: *mut c_void, s: *const c_char);
+    fn chugin_io_flush(io: *mut c_void);
+    fn chugin_log_level() -> chuck::Int;
+}
+
+// ChucK's log levels, as set with `chuck -v<level>`
+pub const LOG_NONE: chuck::Int = 0;
+pub const LOG_CORE: chuck::Int = 1;
+pub const LOG_SYSTEM: chuck::Int = 2;
+pub const LOG_SEVERE: chuck::Int = 3;
+pub const LOG_WARNING: chuck::Int = 4;
+pub const LOG_INFO: chuck::Int = 5;
+pub const LOG_CONFIG: chuck::Int = 6;
+pub const LOG_FINE: chuck::Int = 7;
+pub const LOG_FINER: chuck::Int = 8;
+pub const LOG_FINEST: chuck::Int = 9;
+pub const LOG_CRAZY: chuck::Int = 10;
+
+thread_local! {
+    /// Address of the VM that last invoked a callback on this thread
+    static CURRENT_VM: Cell<usize> = const { Cell::new(0) };
+}
+
+/// chout and cherr of the VM that is querying the chugin
+/// Called by Query::new, which records them as the VM's info
+pub(crate) fn console(query: *mut chuck::DL_Query) -> (*mut c_void, *mut c_void) {
+    unsafe { (chugin_query_chout(query), chugin_query_cherr(query)) }
+}
+
+/// Direct this thread's output to vm's console
+/// Called by Query::new and Context::new, so that printing from a callback
+/// reaches the VM that invoked it
+pub(crate) fn set_current_vm(vm: Vm) {
+    if !vm.as_ptr().is_null() {
+        CURRENT_VM.with(|current| current.set(vm.as_ptr() as usize));
+    }
+}
+
+fn write(io: fn(&VmInfo) -> *mut c_void, s: &str) -> bool {
+    let vm = CURRENT_VM.with(Cell::get);
+    if vm == 0 {
+        return false;
+    }
+
+    let io = match state::info(vm) {
+        Some(info) if !io(&info).is_null() => io(&info),
+        _ => return false,
+    };
+
+    // ChucK strings can't hold NULs, so don't let them cut the message short
+    let s = match CString::new("console output", &s.replace('\0', "\\0")) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+
+    unsafe {
+        chugin_io_write(io, s.c_str());
+        chugin_io_flush(io);
+    }
+
+    true
+}
+
+/// Write to the chout of the VM that last invoked a callback on this thread,
+/// falling back to stdout on threads ChucK hasn't called the chugin from
+pub fn print(s: &str) {
+    if !write(|info| info.chout, s) {
+        print!("{}", s);
+    }
+}
+
+/// Write to the cherr of the VM that last invoked a callback on this thread,
+/// falling back to stderr on threads ChucK hasn't called the chugin from
+pub fn eprint(s: &str) {
+    if !write(|info| info.cherr, s) {
+        eprint!("{}", s);
+    }
+}
+
+/// The VM's log level (`chuck -v<level>`), or None if the host doesn't
+/// export it
+pub fn verbosity() -> Option<chuck::Int> {
+    match unsafe { chugin_log_level() } {
+        level if level < 0 => None,
+        level => Some(level),
+    }
+}
+
+/// Whether a message at the given ChucK log level would be shown
+pub fn enabled(level: chuck::Int) -> bool {
+    level <= verbosity().unwrap_or(LOG_SYSTEM)
+}
+
+/// Print to ChucK's chout, like print!
+#[macro_export]
+macro_rules! ck_print {
+    ($($arg:tt)*) => {
+        chugin::log::print(&format!($($arg)*))
+    };
+}
+
+/// Print a line to ChucK's chout, like println!
+#[macro_export]
+macro_rules! ck_println {
+    () => {
+        chugin::log::print("\n")
+    };
+    ($($arg:tt)*) => {
+        chugin::log::print(&format!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+/// Print to ChucK's cherr, like eprint!
+#[macro_export]
+macro_rules! ck_eprint {
+    ($($arg:tt)*) => {
+        chugin::log::eprint(&format!($($arg)*))
+    };
+}
+
+/// Print a line to ChucK's cherr, like eprintln!
+#[macro_export]
+macro_rules! ck_eprintln {
+    () => {
+        chugin::log::eprint("\n")
+    };
+    ($($arg:tt)*) => {
+        chugin::log::eprint(&format!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+/// Backend for the `log` crate that writes to cherr
+/// Errors and warnings are always shown; info, debug and trace follow the
+/// VM's verbosity (`-v5`, `-v7` and `-v9` respectively)
+#[cfg(feature = "log")]
+pub struct Logger;
+
+#[cfg(feature = "log")]
+static LOGGER: Logger = Logger;
+
+#[cfg(feature = "log")]
+impl Logger {
+    fn ck_level(level: ::log::Level) -> chuck::Int {
+        match level {
+            ::log::Level::Error => LOG_NONE,
+            ::log::Level::Warn => LOG_NONE,
+            ::log::Level::Info => LOG_INFO,
+            ::log::Level::Debug => LOG_FINE,
+            ::log::Level::Trace => LOG_FINEST,
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+impl ::log::Log for Logger {
+    fn enabled(&self, metadata: &::log::Metadata) -> bool {
+        enabled(Logger::ck_level(metadata.level()))
+    }
+
+    fn log(&self, record: &::log::Record) {
+        if self.enabled(record.metadata()) {
+            eprint(&format!(
+                "[{}] {}: {}\n",
+                record.target(),
+                record.level(),
+                record.args()
+            ));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the cherr Logger as the `log` crate's logger
+/// Does nothing if a logger is already installed
+#[cfg(feature = "log")]
+pub fn init_logger() {
+    if ::log::set_logger(&LOGGER).is_ok() {
+        ::log::set_max_level(::log::LevelFilter::Trace);
+    }
+}
@@ -0,0 +1,144 @@
+// Support for running code on the host's main thread
+
+use crate::chuck;
+use crate::{CKResult, Error};
+use std::cell::Cell;
+use std::os::raw::c_void;
+
+/// Closures boxed into the bindle handed to ChucK's main thread hook
+pub(crate) struct Bindle<H, Q> {
+    hook: H,
+    quit: Q,
+}
+
+impl<H, Q> Bindle<H, Q>
+where
+    H: FnMut() + Send + 'static,
+    Q: FnOnce() + Send + 'static,
+{
+    pub(crate) fn new(hook: H, quit: Q) -> Bindle<H, Q> {
+        Bindle { hook, quit }
+    }
+}
+
+/// Called by ChucK on the main thread to run the hook
+pub(crate) unsafe extern "C" fn hook_trampoline<H, Q>(bindle: *mut c_void) -> chuck::t_CKBOOL
+where
+    H: FnMut() + Send + 'static,
+    Q: FnOnce() + Send + 'static,
+{
+    let bindle = match (bindle as *mut Bindle<H, Q>).as_mut() {
+        Some(bindle) => bindle,
+        None => return chuck::CK_FALSE,
+    };
+
+    (bindle.hook)();
+
+    chuck::CK_TRUE
+}
+
+/// Called by ChucK when the hook should stop running; the bindle is freed
+/// after the quit closure returns
+pub(crate) unsafe extern "C" fn quit_trampoline<H, Q>(bindle: *mut c_void) -> chuck::t_CKBOOL
+where
+    H: FnMut() + Send + 'static,
+    Q: FnOnce() + Send + 'static,
+{
+    if bindle.is_null() {
+        return chuck::CK_FALSE;
+    }
+
+    let bindle = Box::from_raw(bindle as *mut Bindle<H, Q>);
+    (bindle.quit)();
+
+    chuck::CK_TRUE
+}
+
+/// Frees a bindle ChucK will no longer call into, without running quit
+pub(crate) unsafe fn drop_bindle<H, Q>(bindle: *mut c_void)
+where
+    H: FnMut() + Send + 'static,
+    Q: FnOnce() + Send + 'static,
+{
+    drop(Box::from_raw(bindle as *mut Bindle<H, Q>));
+}
+
+/// Handle to a main thread hook created by Query::main_thread_hook
+/// Dropping it while the hook isn't active frees the closures, since ChucK
+/// only calls quit for the active hook
+pub struct MainThreadHook {
+    hook: *mut chuck::DL_MainThreadHook,
+    bindle: *mut c_void,
+    drop_bindle: unsafe fn(*mut c_void),
+    /// whether the host is running the hook, and will call quit
+    active: Cell<bool>,
+}
+
+impl MainThreadHook {
+    pub(crate) fn new(
+        hook: *mut chuck::DL_MainThreadHook,
+        bindle: *mut c_void,
+        drop_bindle: unsafe fn(*mut c_void),
+    ) -> CKResult<MainThreadHook> {
+        if !hook.is_null() {
+            Ok(MainThreadHook {
+                hook,
+                bindle,
+                drop_bindle,
+                active: Cell::new(false),
+            })
+        } else {
+            Err(Error::MainThreadHookFailed("create"))
+        }
+    }
+
+    /// Ask the host to start running the hook on its main thread
+    pub fn activate(&self) -> CKResult {
+        let hook = match unsafe { self.hook.as_ref() } {
+            Some(hook) => hook,
+            None => return Err(Error::NullPointer("main thread hook")),
+        };
+
+        let activate = match hook.activate {
+            Some(f) => f,
+            None => return Err(Error::NullPointer("main thread hook")),
+        };
+
+        match unsafe { activate(self.hook) } {
+            0 => Err(Error::MainThreadHookFailed("activate")),
+            _ => {
+                self.active.set(true);
+                Ok(())
+            }
+        }
+    }
+
+    /// Ask the host to stop running the hook
+    pub fn deactivate(&self) -> CKResult {
+        let hook = match unsafe { self.hook.as_ref() } {
+            Some(hook) => hook,
+            None => return Err(Error::NullPointer("main thread hook")),
+        };
+
+        let deactivate = match hook.deactivate {
+            Some(f) => f,
+            None => return Err(Error::NullPointer("main thread hook")),
+        };
+
+        match unsafe { deactivate(self.hook) } {
+            0 => Err(Error::MainThreadHookFailed("deactivate")),
+            _ => {
+                self.active.set(false);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Drop for MainThreadHook {
+    fn drop(&mut self) {
+        if !self.active.get() {
+            unsafe { (self.drop_bindle)(self.bindle) };
+        }
+    }
+}
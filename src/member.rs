@@ -0,0 +1,135 @@
+// Typed access to member variables that ChucK code can read and write
+
+use crate::chuck;
+use crate::ckref::{CkObject, CkRef};
+use crate::data_offset::DataOffset;
+use std::marker::PhantomData;
+
+/// Rust types that can be stored in a ChucK member variable
+pub trait MemberType {
+    /// name of the corresponding type in ChucK
+    const TYPE_NAME: &'static str;
+
+    /// The value read from and written to the variable
+    type Value;
+
+    /// Unsafe because slot must point to a variable of this type
+    unsafe fn read(slot: *mut u8) -> Self::Value;
+
+    /// Unsafe because slot must point to a variable of this type
+    unsafe fn write(slot: *mut u8, value: Self::Value);
+}
+
+impl MemberType for chuck::Float {
+    const TYPE_NAME: &'static str = "float";
+    type Value = chuck::Float;
+
+    unsafe fn read(slot: *mut u8) -> Self::Value {
+        *(slot as *const chuck::Float)
+    }
+
+    unsafe fn write(slot: *mut u8, value: Self::Value) {
+        *(slot as *mut chuck::Float) = value;
+    }
+}
+
+impl MemberType for chuck::Int {
+    const TYPE_NAME: &'static str = "int";
+    type Value = chuck::Int;
+
+    unsafe fn read(slot: *mut u8) -> Self::Value {
+        *(slot as *const chuck::Int)
+    }
+
+    unsafe fn write(slot: *mut u8, value: Self::Value) {
+        *(slot as *mut chuck::Int) = value;
+    }
+}
+
+/// Object variables hold a counted reference; reading takes a new one and
+/// writing releases the previous object
+impl<T: CkObject> MemberType for CkRef<T> {
+    const TYPE_NAME: &'static str = T::TYPE_NAME;
+    type Value = Option<CkRef<T>>;
+
+    unsafe fn read(slot: *mut u8) -> Self::Value {
+        CkRef::new(*(slot as *const *mut T))
+    }
+
+    unsafe fn write(slot: *mut u8, value: Self::Value) {
+        let slot = slot as *mut *mut T;
+        let old = *slot;
+
+        *slot = match value {
+            Some(value) => value.into_raw(),
+            None => std::ptr::null_mut(),
+        };
+
+        drop(CkRef::from_raw(old));
+    }
+}
+
+/// Handle to a member variable of a class, e.g. a public `cutoff` that
+/// ChucK code sets and tick reads
+/// Declare one per variable as a plain `static`, like DataOffset, and add it
+/// with ClassBuilder::member or Query::add_typed_mvar
+pub struct MemberVar<T: MemberType> {
+    name: &'static str,
+    is_const: bool,
+    offset: DataOffset,
+    type_: PhantomData<fn() -> T>,
+}
+
+impl<T: MemberType> MemberVar<T> {
+    pub const fn new(name: &'static str) -> MemberVar<T> {
+        MemberVar {
+            name,
+            is_const: false,
+            offset: DataOffset::new(),
+            type_: PhantomData,
+        }
+    }
+
+    /// A variable ChucK code can read but not assign
+    pub const fn new_const(name: &'static str) -> MemberVar<T> {
+        MemberVar {
+            name,
+            is_const: true,
+            offset: DataOffset::new(),
+            type_: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn is_const(&self) -> bool {
+        self.is_const
+    }
+
+    /// Record the offset ChucK assigned to the variable
+    pub(crate) fn offset(&self) -> &DataOffset {
+        &self.offset
+    }
+
+    unsafe fn slot(&self, obj: *const chuck::Object) -> *mut u8 {
+        (*obj).data.add(self.offset.get())
+    }
+
+    /// Read the variable
+    /// Panics if the variable hasn't been added to the class
+    /// Unsafe because obj must be a live object of the class the variable
+    /// was added to, or of a subclass
+    pub unsafe fn get(&self, obj: *const chuck::Object) -> T::Value {
+        T::read(self.slot(obj))
+    }
+
+    /// Write the variable
+    /// Panics if the variable hasn't been added to the class
+    /// Unsafe because obj must be a live object of the class the variable
+    /// was added to, or of a subclass
+    pub unsafe fn set(&self, obj: *mut chuck::Object, value: T::Value) {
+        T::write(self.slot(obj), value)
+    }
+}
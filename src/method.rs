@@ -0,0 +1,227 @@
+// Member functions registered from plain Rust functions and closures
+
+use crate::args::{ArgList, ArgType};
+use crate::chuck;
+use crate::ckref::{CkObject, CkRef};
+use crate::context::Context;
+use crate::data_offset::DataOffset;
+use crate::error::DataError;
+use crate::util;
+use std::marker::PhantomData;
+
+/// A Rust type stored as the data of a ChucK object
+pub trait ChuginObject: Sized + 'static {
+    /// Offset of the `@data` member variable holding the object
+    fn data_offset() -> &'static DataOffset;
+
+    /// Borrow the data of ck_self
+    /// Overridden for subclasses, whose data lives inside the base's
+    /// Unsafe because ck_self must be an object of a class holding Self
+    unsafe fn with_data<R>(ck_self: *mut chuck::Object, f: impl FnOnce(&mut Self) -> R) -> Result<R, DataError> {
+        util::try_with_object_data(ck_self, Self::data_offset().get(), f)
+    }
+
+    /// Store value as the data of a new object
+    /// Unsafe because ck_self must be an object of a class holding Self
+    unsafe fn set_data(ck_self: *mut chuck::Object, value: Self) {
+        util::set_object_data(ck_self, Self::data_offset().get(), Box::new(value));
+    }
+}
+
+/// trait for values a member function can return to ChucK
+pub trait CKReturn {
+    /// name of the corresponding type in ChucK
+    const TYPE_NAME: &'static str;
+
+    /// Store the value in a callback's return slot; `ck_self` is the object
+    /// the callback was invoked on
+    /// Objects are returned the way ChucK's own native functions return
+    /// them, without a reference for the caller: ChucK adds one when it
+    /// stores the result, and an object nothing references is left at a
+    /// count of 0, like one ChucK just created. Implementations returning a
+    /// counted reference give it up without deleting the object
+    /// Unsafe because ret must point to a valid return slot
+    unsafe fn encode(self, ck_self: *mut chuck::Object, ret: *mut chuck::Chuck_DL_Return);
+}
+
+impl CKReturn for () {
+    const TYPE_NAME: &'static str = "void";
+
+    unsafe fn encode(self, _ck_self: *mut chuck::Object, _ret: *mut chuck::Chuck_DL_Return) {}
+}
+
+impl CKReturn for chuck::Float {
+    const TYPE_NAME: &'static str = "float";
+
+    unsafe fn encode(self, _ck_self: *mut chuck::Object, ret: *mut chuck::Chuck_DL_Return) {
+        (*ret).v_float = self;
+    }
+}
+
+impl CKReturn for f32 {
+    const TYPE_NAME: &'static str = "float";
+
+    unsafe fn encode(self, _ck_self: *mut chuck::Object, ret: *mut chuck::Chuck_DL_Return) {
+        (*ret).v_float = self as f64;
+    }
+}
+
+impl CKReturn for chuck::Int {
+    const TYPE_NAME: &'static str = "int";
+
+    unsafe fn encode(self, _ck_self: *mut chuck::Object, ret: *mut chuck::Chuck_DL_Return) {
+        (*ret).v_int = self;
+    }
+}
+
+/// Return value of a method that returns the object it was called on, so
+/// calls can be chained, e.g. `chain.add(1).add(2)`
+/// Registered with the name of the class being constructed as return type
+pub struct This;
+
+impl CKReturn for This {
+    /// replaced by the class name when the method is added
+    const TYPE_NAME: &'static str = "@this";
+
+    unsafe fn encode(self, ck_self: *mut chuck::Object, ret: *mut chuck::Chuck_DL_Return) {
+        (*ret).v_object = ck_self;
+    }
+}
+
+/// The reference held by the CkRef is given up, see CKReturn::encode
+impl<T: CkObject> CKReturn for CkRef<T> {
+    const TYPE_NAME: &'static str = T::TYPE_NAME;
+
+    unsafe fn encode(self, _ck_self: *mut chuck::Object, ret: *mut chuck::Chuck_DL_Return) {
+        (*ret).v_object = self.into_return_value() as *mut chuck::Object;
+    }
+}
+
+/// None returns null
+impl<T: CkObject> CKReturn for Option<CkRef<T>> {
+    const TYPE_NAME: &'static str = T::TYPE_NAME;
+
+    unsafe fn encode(self, ck_self: *mut chuck::Object, ret: *mut chuck::Chuck_DL_Return) {
+        match self {
+            Some(obj) => obj.encode(ck_self, ret),
+            None => (*ret).v_object = std::ptr::null_mut(),
+        }
+    }
+}
+
+/// A function or closure without captures taking the object and arguments
+/// decoded from ChucK
+/// Implemented for `Fn(&mut T, A, B, ...) -> R` with up to 6 arguments
+pub trait Method<T, A: ArgList, R: CKReturn>: Copy + 'static {
+    fn call(self, obj: &mut T, args: A) -> R;
+}
+
+impl<T, R, F> Method<T, (), R> for F
+where
+    F: Fn(&mut T) -> R + Copy + 'static,
+    R: CKReturn,
+{
+    fn call(self, obj: &mut T, _args: ()) -> R {
+        self(obj)
+    }
+}
+
+macro_rules! impl_method {
+    ($($a:ident),+) => {
+        impl<T, R, F, $($a),+> Method<T, ($($a,)+), R> for F
+        where
+            F: Fn(&mut T, $($a),+) -> R + Copy + 'static,
+            R: CKReturn,
+            $($a: ArgType,)+
+        {
+            #[allow(non_snake_case)]
+            fn call(self, obj: &mut T, ($($a,)+): ($($a,)+)) -> R {
+                self(obj, $($a),+)
+            }
+        }
+    };
+}
+
+impl_method!(A);
+impl_method!(A, B);
+impl_method!(A, B, C);
+impl_method!(A, B, C, D);
+impl_method!(A, B, C, D, E);
+impl_method!(A, B, C, D, E, F1);
+
+/// Fails to compile for a Method (or a dspz constructor) that captures
+/// state, since the trampoline recreates it from its type alone
+pub(crate) struct AssertNoCaptures<F>(PhantomData<F>);
+
+impl<F> AssertNoCaptures<F> {
+    pub(crate) const OK: () = assert!(
+        std::mem::size_of::<F>() == 0,
+        "methods and constructors must be functions or closures without captures"
+    );
+}
+
+/// The monomorphized mfun for a Method; F is zero-sized, so it is recreated
+/// from its type alone
+unsafe extern "C" fn trampoline<T, A, R, F>(
+    ck_self: *mut chuck::Object,
+    args: *mut ::std::os::raw::c_void,
+    return_: *mut chuck::Chuck_DL_Return,
+    vm: *mut chuck::VM,
+    shred: *mut chuck::VM_Shred,
+    api: chuck::CK_DL_API,
+) where
+    T: ChuginObject,
+    A: ArgList,
+    R: CKReturn,
+    F: Method<T, A, R>,
+{
+    let () = AssertNoCaptures::<F>::OK;
+
+    let f: F = std::mem::zeroed();
+    let ctx = Context::new(vm, shred, api).with_object(ck_self);
+    let args = A::decode(args, &ctx);
+
+    let ret = T::with_data(ck_self, |obj| f.call(obj, args));
+
+    match ret {
+        Ok(ret) => ret.encode(ck_self, return_),
+        Err(e) => crate::log::eprint(&format!("[chugin]: method called, but the {}\n", e)),
+    }
+}
+
+/// A Method ready to be registered, with its ChucK signature
+pub struct MethodFn<T, A, R> {
+    mfun: chuck::f_mfun,
+    types: PhantomData<fn(T, A) -> R>,
+}
+
+impl<T: ChuginObject, A: ArgList, R: CKReturn> MethodFn<T, A, R> {
+    /// Fails to compile if `f` captures anything, since a capture-free mfun
+    /// can't carry it
+    pub fn new<F: Method<T, A, R>>(_f: F) -> MethodFn<T, A, R> {
+        let () = AssertNoCaptures::<F>::OK;
+
+        MethodFn {
+            mfun: Some(trampoline::<T, A, R, F>),
+            types: PhantomData,
+        }
+    }
+
+    pub fn get(&self) -> chuck::f_mfun {
+        self.mfun
+    }
+
+    /// ChucK return type name
+    pub fn return_type(&self) -> &'static str {
+        R::TYPE_NAME
+    }
+
+    /// (type, name) pairs for the arguments, named arg0, arg1, ...
+    pub fn args(&self) -> Vec<(String, String)> {
+        A::type_names()
+            .into_iter()
+            .enumerate()
+            .map(|(i, type_)| (type_.to_string(), format!("arg{}", i)))
+            .collect()
+    }
+}
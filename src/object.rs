@@ -0,0 +1,161 @@
+// Classes extending Object whose data is a plain Rust type
+
+use crate::chuck;
+use crate::ckref::CkRef;
+use crate::args::ArgType;
+use crate::cktype::CKType;
+use crate::class::{ClassBuilder, ObjectKind};
+use crate::context::Context;
+use crate::method::{CKReturn, ChuginObject};
+use crate::query::Query;
+use crate::util;
+use crate::error::DataError;
+use crate::{CKResult, Error};
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+
+/// A Rust type registered as a ChucK class of its own
+pub trait ChuginClass: ChuginObject {
+    /// name of the class in ChucK
+    const CLASS_NAME: &'static str;
+}
+
+/// Data that a class's ctor creates when ChucK instantiates the class, for
+/// object_class and subclass()
+pub trait ChuginCtor: Sized {
+    /// Create the data of a new object
+    fn new(ctx: &Context) -> Self;
+}
+
+extern "C" fn ctor<T: ChuginClass + ChuginCtor>(
+    ck_self: *mut chuck::Object,
+    _args: *mut c_void,
+    vm: *mut chuck::VM,
+    shred: *mut chuck::VM_Shred,
+    api: chuck::CK_DL_API,
+) {
+    let ctx = Context::new(vm, shred, api).with_object(ck_self);
+    unsafe { T::set_data(ck_self, T::new(&ctx)) };
+}
+
+extern "C" fn dtor<T: ChuginClass>(
+    ck_self: *mut chuck::Object,
+    _vm: *mut chuck::VM,
+    _shred: *mut chuck::VM_Shred,
+    _api: chuck::CK_DL_API,
+) {
+    drop(unsafe { util::get_object_data::<T>(ck_self, T::data_offset().get()) });
+}
+
+impl Query {
+    /// Start a class extending Object for T, with a ctor creating T with
+    /// ChuginCtor::new, a dtor and the `@data` member variable; finish() it
+    /// after adding methods
+    pub fn object_class<T: ChuginClass + ChuginCtor>(&self) -> ClassBuilder<'_, ObjectKind> {
+        self.class(T::CLASS_NAME)
+            .extends("Object")
+            .ctor(Some(ctor::<T>))
+            .dtor(Some(dtor::<T>))
+            .data(T::data_offset())
+    }
+}
+
+impl Context {
+    /// Create a new object of T's class holding `value`, e.g. to return from
+    /// a method
+    /// ChucK doesn't run class ctors for objects created this way, so the
+    /// data is given here instead
+    /// The Instance holds the only reference; returning it leaves the object
+    /// to ChucK, and dropping it deletes the object
+    pub fn create_instance<T: ChuginClass>(&self, value: T) -> CKResult<Instance<T>> {
+        let obj = self.create(self.get_type(T::CLASS_NAME)?)?;
+
+        unsafe {
+            T::set_data(obj, value);
+
+            match CkRef::new(obj) {
+                Some(obj) => Ok(Instance::from_ref_unchecked(obj)),
+                None => Err(Error::NullPointer("created object")),
+            }
+        }
+    }
+}
+
+/// Counted reference to a ChucK object of a Rust-backed class
+/// Rust data can hold these to keep other instances alive, and methods can
+/// return them as objects of class T
+pub struct Instance<T: ChuginClass> {
+    obj: CkRef<chuck::Object>,
+    type_: PhantomData<T>,
+}
+
+impl<T: ChuginClass> Instance<T> {
+    /// Unsafe because obj must be an object of T's class whose ctor has run
+    pub unsafe fn from_ref_unchecked(obj: CkRef<chuck::Object>) -> Instance<T> {
+        Instance {
+            obj,
+            type_: PhantomData,
+        }
+    }
+
+    /// Check that obj is of T's class or a subclass and take a new
+    /// reference to it; ctx is that of the callback that received obj
+    pub fn downcast(obj: &CkRef<chuck::Object>, ctx: &Context) -> Option<Instance<T>> {
+        if obj.is_instance_of(ctx, T::CLASS_NAME) {
+            Some(unsafe { Instance::from_ref_unchecked(obj.clone()) })
+        } else {
+            None
+        }
+    }
+
+    /// The underlying object
+    pub fn object(&self) -> &CkRef<chuck::Object> {
+        &self.obj
+    }
+
+    pub fn into_ref(self) -> CkRef<chuck::Object> {
+        self.obj
+    }
+
+    /// Borrow the Rust data of the instance; fails with DataError::Borrowed
+    /// if a method is running on this same object
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R, DataError> {
+        unsafe { T::with_data(self.obj.as_ptr(), f) }
+    }
+}
+
+/// Arguments of another class, or null, decode as None
+impl<T: ChuginClass> ArgType for Option<Instance<T>> {
+    const TYPE_NAME: &'static str = T::CLASS_NAME;
+
+    fn get_next_arg_in(args: chuck::Args, ctx: &Context) -> (chuck::Args, Self) {
+        let (args, obj) = <Option<CkRef<chuck::Object>>>::get_next_arg(args);
+        (args, obj.and_then(|obj| Instance::downcast(&obj, ctx)))
+    }
+}
+
+impl<T: ChuginClass> Clone for Instance<T> {
+    fn clone(&self) -> Self {
+        Instance {
+            obj: self.obj.clone(),
+            type_: PhantomData,
+        }
+    }
+}
+
+impl<T: ChuginClass> CKReturn for Instance<T> {
+    const TYPE_NAME: &'static str = T::CLASS_NAME;
+
+    unsafe fn encode(self, ck_self: *mut chuck::Object, ret: *mut chuck::Chuck_DL_Return) {
+        self.obj.encode(ck_self, ret)
+    }
+}
+
+/// None returns null
+impl<T: ChuginClass> CKReturn for Option<Instance<T>> {
+    const TYPE_NAME: &'static str = T::CLASS_NAME;
+
+    unsafe fn encode(self, ck_self: *mut chuck::Object, ret: *mut chuck::Chuck_DL_Return) {
+        self.map(Instance::into_ref).encode(ck_self, ret)
+    }
+}
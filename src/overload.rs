@@ -0,0 +1,72 @@
+// Sets of member functions that share one ChucK name
+
+use crate::args::{ArgList, ArgSpec, Mfun};
+use crate::chuck;
+use crate::method::{CKReturn, ChuginObject, Method, MethodFn};
+
+/// One signature of an overload set
+pub(crate) struct Signature {
+    pub mfun: chuck::f_mfun,
+    pub type_: String,
+    pub args: Vec<(String, String)>,
+}
+
+/// Member functions registered under one name, e.g. `float freq(float)` and
+/// `float freq()`
+/// Added with Query::add_overloads or ClassBuilder::overloads; a signature
+/// that is already defined fails the query
+pub struct Overloads {
+    name: String,
+    signatures: Vec<Signature>,
+}
+
+impl Overloads {
+    pub fn new(name: &str) -> Overloads {
+        Overloads {
+            name: name.to_string(),
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Add a signature
+    pub fn mfun(mut self, mfun: chuck::f_mfun, type_: &str, args: &[(String, String)]) -> Self {
+        self.signatures.push(Signature {
+            mfun,
+            type_: type_.to_string(),
+            args: args.to_vec(),
+        });
+        self
+    }
+
+    /// Add a signature with checked argument types
+    pub fn typed_mfun<A: ArgList>(self, mfun: Mfun<A>, type_: &str, args: ArgSpec<A>) -> Self {
+        self.mfun(mfun.get(), type_, &args.to_pairs())
+    }
+
+    /// Add a signature implemented by a plain Rust function or closure
+    /// without captures; see Query::add_method
+    pub fn method<T, A, R, F>(self, f: F) -> Self
+    where
+        T: ChuginObject,
+        A: ArgList,
+        R: CKReturn,
+        F: Method<T, A, R>,
+    {
+        let method = MethodFn::new(f);
+        self.mfun(method.get(), method.return_type(), &method.args())
+    }
+
+    /// Add `float name(float)` and `float name()`
+    pub fn setter_getter(self, setter: chuck::f_mfun, getter: chuck::f_mfun) -> Self {
+        self.mfun(setter, "float", &[(String::from("float"), String::from("value"))])
+            .mfun(getter, "float", &[])
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn signatures(&self) -> &[Signature] {
+        &self.signatures
+    }
+}
@@ -0,0 +1,434 @@
+// Parameters that ramp to new values instead of jumping
+
+/// Numeric types a Smoothed parameter can hold
+pub trait Smoothable: Copy {
+    fn to_f64(self) -> f64;
+    fn from_f64(value: f64) -> Self;
+}
+
+impl Smoothable for f32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
+
+impl Smoothable for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+/// Shape of the ramp towards a new target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ramp {
+    /// reach the target in exactly the ramp time
+    Linear,
+    /// exponential approach; the ramp time is the time constant (~63%)
+    OnePole,
+}
+
+/// Length of a ramp
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RampTime {
+    Samples(u32),
+    Ms(f64),
+}
+
+impl RampTime {
+    fn samples(self, srate: f64) -> f64 {
+        match self {
+            RampTime::Samples(n) => n as f64,
+            RampTime::Ms(ms) => (ms * srate / 1000.0).max(0.0),
+        }
+    }
+}
+
+// one-pole ramps snap to the target once this close
+const EPSILON: f64 = 1e-9;
+
+/// A parameter that moves to new values over time to avoid zipper noise
+/// Call advance() once per sample, e.g. with the `smoothed [...]` form of
+/// tick!
+#[derive(Debug, Clone)]
+pub struct Smoothed<T: Smoothable> {
+    ramp: Ramp,
+    time: RampTime,
+    srate: f64,
+    current: f64,
+    target: f64,
+    /// per-sample increment (linear) or coefficient (one-pole)
+    step: f64,
+    /// samples left in a linear ramp
+    remaining: u32,
+    changed: bool,
+    _type: std::marker::PhantomData<T>,
+}
+
+impl<T: Smoothable> Smoothed<T> {
+    /// Create a parameter at `value`; `srate` is the host sample rate, used
+    /// for ramp times in ms
+    pub fn new(value: T, ramp: Ramp, time: RampTime, srate: f64) -> Self {
+        let value = value.to_f64();
+
+        let mut smoothed = Smoothed {
+            ramp,
+            time,
+            srate,
+            current: value,
+            target: value,
+            step: 0.0,
+            remaining: 0,
+            changed: false,
+            _type: std::marker::PhantomData,
+        };
+        smoothed.update_step();
+        smoothed
+    }
+
+    /// Create a linearly ramped parameter
+    pub fn linear(value: T, time: RampTime, srate: f64) -> Self {
+        Smoothed::new(value, Ramp::Linear, time, srate)
+    }
+
+    /// Create an exponentially ramped parameter
+    pub fn one_pole(value: T, time: RampTime, srate: f64) -> Self {
+        Smoothed::new(value, Ramp::OnePole, time, srate)
+    }
+
+    /// Start ramping towards a new value
+    pub fn set_target(&mut self, target: T) {
+        self.target = target.to_f64();
+        self.update_step();
+    }
+
+    /// Jump straight to a value
+    pub fn set_immediate(&mut self, value: T) {
+        self.current = value.to_f64();
+        self.target = self.current;
+        self.remaining = 0;
+        self.changed = true;
+    }
+
+    /// Change the ramp time; a ramp in progress continues from where it is
+    pub fn set_time(&mut self, time: RampTime) {
+        self.time = time;
+        self.update_step();
+    }
+
+    /// Change the sample rate used for ramp times in ms
+    pub fn set_srate(&mut self, srate: f64) {
+        self.srate = srate;
+        self.update_step();
+    }
+
+    /// The value being ramped towards
+    pub fn target(&self) -> T {
+        T::from_f64(self.target)
+    }
+
+    /// The current value
+    pub fn value(&self) -> T {
+        T::from_f64(self.current)
+    }
+
+    /// Whether the value is still moving
+    pub fn is_smoothing(&self) -> bool {
+        self.current != self.target
+    }
+
+    /// Whether the last advance() (or set_immediate) changed the value
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Move one sample along the ramp; returns whether the value changed
+    pub fn advance(&mut self) -> bool {
+        if !self.is_smoothing() {
+            self.changed = false;
+            return false;
+        }
+
+        match self.ramp {
+            Ramp::Linear => {
+                if self.remaining <= 1 {
+                    self.current = self.target;
+                    self.remaining = 0;
+                } else {
+                    self.current += self.step;
+                    self.remaining -= 1;
+                }
+            }
+            Ramp::OnePole => {
+                self.current = self.target + (self.current - self.target) * self.step;
+                if (self.current - self.target).abs() < EPSILON {
+                    self.current = self.target;
+                }
+            }
+        }
+
+        self.changed = true;
+        true
+    }
+
+    /// Advance one sample and return the new value
+    pub fn next_value(&mut self) -> T {
+        self.advance();
+        self.value()
+    }
+
+    fn update_step(&mut self) {
+        let samples = self.time.samples(self.srate);
+
+        match self.ramp {
+            Ramp::Linear => {
+                self.remaining = samples.round() as u32;
+                self.step = if self.remaining > 0 {
+                    (self.target - self.current) / self.remaining as f64
+                } else {
+                    0.0
+                };
+            }
+            Ramp::OnePole => {
+                self.step = if samples > 0.0 {
+                    (-1.0 / samples).exp()
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+}
+
+/// How a parameter is best displayed or swept
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scaling {
+    Linear,
+    Log,
+}
+
+/// What a setter does with values outside a parameter's range
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutOfRange {
+    /// use the nearest value in range
+    Clamp,
+    /// keep the previous value
+    Reject,
+}
+
+/// Description of a float parameter: its range, default, unit and scaling
+/// Pass it to the setter macros to check values and to ClassBuilder::param
+/// to document the range
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamInfo {
+    pub name: &'static str,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    pub unit: &'static str,
+    pub scaling: Scaling,
+    pub out_of_range: OutOfRange,
+    /// also limit the maximum to half the host sample rate
+    pub below_nyquist: bool,
+}
+
+impl ParamInfo {
+    /// Describe a linear, unitless parameter that clamps out-of-range values
+    pub const fn new(name: &'static str, min: f64, max: f64, default: f64) -> ParamInfo {
+        ParamInfo {
+            name,
+            min,
+            max,
+            default,
+            unit: "",
+            scaling: Scaling::Linear,
+            out_of_range: OutOfRange::Clamp,
+            below_nyquist: false,
+        }
+    }
+
+    pub const fn unit(mut self, unit: &'static str) -> ParamInfo {
+        self.unit = unit;
+        self
+    }
+
+    pub const fn scaling(mut self, scaling: Scaling) -> ParamInfo {
+        self.scaling = scaling;
+        self
+    }
+
+    /// Keep the previous value instead of clamping
+    pub const fn reject(mut self) -> ParamInfo {
+        self.out_of_range = OutOfRange::Reject;
+        self
+    }
+
+    pub const fn below_nyquist(mut self) -> ParamInfo {
+        self.below_nyquist = true;
+        self
+    }
+
+    /// The upper bound at the given sample rate
+    pub fn max_at(&self, srate: Option<f64>) -> f64 {
+        match srate {
+            Some(srate) if self.below_nyquist => self.max.min(srate / 2.0),
+            _ => self.max,
+        }
+    }
+
+    /// Check a value passed to the setter, warning on cherr if it is out of
+    /// range; returns the value to use, or None to keep the previous one
+    /// NaN and infinities are always ignored, whatever the OutOfRange policy
+    pub fn check(&self, value: f64, srate: Option<f64>) -> Option<f64> {
+        if !value.is_finite() {
+            crate::log::eprint(&format!("[chugin]: {} = {} is not finite; ignored\n", self.name, value));
+            return None;
+        }
+
+        let max = self.max_at(srate);
+
+        if value >= self.min && value <= max {
+            return Some(value);
+        }
+
+        match self.out_of_range {
+            OutOfRange::Clamp => {
+                let clamped = if value < self.min { self.min } else { max };
+                crate::log::eprint(&format!(
+                    "[chugin]: {} = {} is out of range [{}, {}]; using {}\n",
+                    self.name, value, self.min, max, clamped
+                ));
+                Some(clamped)
+            }
+            OutOfRange::Reject => {
+                crate::log::eprint(&format!(
+                    "[chugin]: {} = {} is out of range [{}, {}]; ignored\n",
+                    self.name, value, self.min, max
+                ));
+                None
+            }
+        }
+    }
+
+    /// Documentation text for ckdoc, e.g. "range 20 to 20000 Hz (log), default 1000"
+    pub fn doc(&self) -> String {
+        let unit = if self.unit.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", self.unit)
+        };
+
+        let max = if self.below_nyquist {
+            format!("min({}, nyquist)", self.max)
+        } else {
+            self.max.to_string()
+        };
+
+        let scaling = match self.scaling {
+            Scaling::Linear => "",
+            Scaling::Log => " (log)",
+        };
+
+        format!(
+            "range {} to {}{}{}, default {}{}",
+            self.min, max, unit, scaling, self.default, unit
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_ramp_ends_on_target() {
+        let mut p = Smoothed::linear(0.0f64, RampTime::Samples(4), 48000.0);
+        p.set_target(1.0);
+
+        let values: Vec<f64> = (0..4).map(|_| p.next_value()).collect();
+        assert_eq!(values, [0.25, 0.5, 0.75, 1.0]);
+        assert!(!p.is_smoothing());
+
+        assert!(!p.advance());
+        assert_eq!(p.value(), 1.0);
+    }
+
+    #[test]
+    fn linear_ramp_time_in_ms() {
+        let mut p = Smoothed::linear(1.0f32, RampTime::Ms(10.0), 1000.0);
+        p.set_target(0.0);
+
+        for _ in 0..9 {
+            p.advance();
+            assert!(p.is_smoothing());
+        }
+        assert_eq!(p.next_value(), 0.0);
+        assert!(!p.is_smoothing());
+    }
+
+    #[test]
+    fn one_pole_ramp_settles_without_overshoot() {
+        let mut p = Smoothed::one_pole(0.0f64, RampTime::Samples(10), 48000.0);
+        p.set_target(1.0);
+
+        let first = p.next_value();
+        assert!((first - (1.0 - (-0.1f64).exp())).abs() < 1e-12);
+
+        let mut last = first;
+        while p.advance() {
+            assert!(p.value() >= last && p.value() <= 1.0);
+            last = p.value();
+        }
+        assert_eq!(p.value(), 1.0);
+    }
+
+    #[test]
+    fn zero_ramp_time_jumps() {
+        let mut linear = Smoothed::linear(0.0f64, RampTime::Samples(0), 48000.0);
+        let mut one_pole = Smoothed::one_pole(0.0f64, RampTime::Ms(0.0), 48000.0);
+        linear.set_target(2.0);
+        one_pole.set_target(2.0);
+
+        assert_eq!(linear.next_value(), 2.0);
+        assert_eq!(one_pole.next_value(), 2.0);
+    }
+
+    #[test]
+    fn set_immediate_skips_the_ramp() {
+        let mut p = Smoothed::linear(0.0f64, RampTime::Samples(100), 48000.0);
+        p.set_target(1.0);
+        p.advance();
+        p.set_immediate(0.5);
+
+        assert!(p.changed());
+        assert!(!p.is_smoothing());
+        assert_eq!(p.target(), 0.5);
+        assert!(!p.advance());
+    }
+
+    #[test]
+    fn check_clamps_or_rejects() {
+        let clamp = ParamInfo::new("freq", 20.0, 20000.0, 1000.0).below_nyquist();
+        assert_eq!(clamp.check(440.0, None), Some(440.0));
+        assert_eq!(clamp.check(5.0, None), Some(20.0));
+        assert_eq!(clamp.check(30000.0, Some(32000.0)), Some(16000.0));
+
+        let reject = clamp.reject();
+        assert_eq!(reject.check(5.0, None), None);
+    }
+
+    #[test]
+    fn check_ignores_non_finite_values() {
+        let info = ParamInfo::new("k", 0.0, 2.0, 1.0);
+        assert_eq!(info.check(f64::NAN, None), None);
+        assert_eq!(info.check(f64::INFINITY, None), None);
+        assert_eq!(info.check(f64::NEG_INFINITY, None), None);
+    }
+}
@@ -0,0 +1,240 @@
+// Saving and restoring a class's registered parameters as JSON presets
+
+use crate::chuck;
+use crate::ckref::CkString;
+use crate::cktype::CKType;
+use crate::context::Context;
+use crate::{CKResult, Error};
+use serde_json::{Map, Value};
+use std::sync::{Arc, Mutex};
+
+/// A parameter added with ClassBuilder::param
+#[derive(Clone, Copy)]
+pub(crate) struct PresetParam {
+    pub name: &'static str,
+    pub setter: chuck::f_mfun,
+    pub getter: chuck::f_mfun,
+}
+
+/// Parameters of each class with presets, keyed by its ChucK class name
+/// Classes can share a data type, e.g. through chugin::dspz or subclass(), so
+/// the type alone doesn't tell which parameters an object has
+static REGISTRY: Mutex<Vec<(String, Arc<Vec<PresetParam>>)>> = Mutex::new(Vec::new());
+
+pub(crate) fn register(class: &str, params: Vec<PresetParam>) {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let params = Arc::new(params);
+
+    match registry.iter_mut().find(|(name, _)| name == class) {
+        Some(entry) => entry.1 = params,
+        None => registry.push((class.to_string(), params)),
+    }
+}
+
+/// Parameters of the registered class obj is an instance of; for objects of
+/// a ChucK subclass, those of the most derived registered class it extends
+fn params(ctx: &Context, obj: *mut chuck::Object) -> Arc<Vec<PresetParam>> {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let api = ctx.api();
+
+    let type_ = match unsafe { api.type_of(obj) } {
+        Ok(type_) => type_,
+        Err(_) => return Arc::default(),
+    };
+    let isa = |type_, parent| matches!(unsafe { api.isa(type_, parent) }, Ok(true));
+
+    let mut found: Option<(*mut chuck::Type, &Arc<Vec<PresetParam>>)> = None;
+
+    for (name, params) in registry.iter() {
+        let class = match ctx.get_type(name) {
+            Ok(class) => class,
+            Err(_) => continue,
+        };
+
+        if !isa(type_, class) {
+            continue;
+        }
+
+        match found {
+            Some((best, _)) if !isa(class, best) => {}
+            _ => found = Some((class, params)),
+        }
+    }
+
+    found.map(|(_, params)| params.clone()).unwrap_or_default()
+}
+
+/// The object and callback arguments the parameter mfuns are called with
+struct Target {
+    ck_self: *mut chuck::Object,
+    vm: *mut chuck::VM,
+    shred: *mut chuck::VM_Shred,
+    api: chuck::CK_DL_API,
+}
+
+impl Target {
+    fn get(&self, getter: chuck::f_mfun) -> Option<f64> {
+        let getter = getter?;
+        let mut ret: chuck::Chuck_DL_Return = unsafe { std::mem::zeroed() };
+
+        unsafe {
+            getter(
+                self.ck_self,
+                std::ptr::null_mut(),
+                &mut ret,
+                self.vm,
+                self.shred,
+                self.api,
+            );
+            Some(ret.v_float)
+        }
+    }
+
+    fn set(&self, setter: chuck::f_mfun, value: f64) {
+        let setter = match setter {
+            Some(f) => f,
+            None => return,
+        };
+        let mut args = [value];
+        let mut ret: chuck::Chuck_DL_Return = unsafe { std::mem::zeroed() };
+
+        unsafe {
+            setter(
+                self.ck_self,
+                args.as_mut_ptr() as *mut ::std::os::raw::c_void,
+                &mut ret,
+                self.vm,
+                self.shred,
+                self.api,
+            );
+        }
+    }
+
+    fn save(&self, params: &[PresetParam]) -> CKResult<String> {
+        let mut map = Map::new();
+
+        for param in params {
+            if let Some(value) = self.get(param.getter) {
+                map.insert(param.name.to_string(), Value::from(value));
+            }
+        }
+
+        serde_json::to_string_pretty(&Value::Object(map))
+            .map_err(|e| Error::Custom(format!("unable to write preset: {}", e)))
+    }
+
+    fn load(&self, params: &[PresetParam], json: &str) -> CKResult {
+        let map = match serde_json::from_str(json) {
+            Ok(Value::Object(map)) => map,
+            Ok(_) => return Err(Error::from("preset is not a JSON object")),
+            Err(e) => return Err(Error::Custom(format!("unable to read preset: {}", e))),
+        };
+
+        for key in map.keys() {
+            if !params.iter().any(|param| param.name == key) {
+                warn(&format!("unknown parameter '{}'", key));
+            }
+        }
+
+        for param in params {
+            match map.get(param.name).map(Value::as_f64) {
+                Some(Some(value)) => self.set(param.setter, value),
+                Some(None) => warn(&format!("parameter '{}' is not a number", param.name)),
+                None => warn(&format!("missing parameter '{}'", param.name)),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn warn(msg: &str) {
+    crate::log::eprint(&format!("[chugin]: preset: {}\n", msg));
+}
+
+fn path_arg(args: *mut ::std::os::raw::c_void) -> Option<String> {
+    let (_, path) = <Option<CkString> as CKType>::get_next_arg(args as chuck::Args);
+    path?.to_str().map(String::from)
+}
+
+fn report(result: CKResult) -> chuck::Int {
+    match result {
+        Ok(()) => 1,
+        Err(e) => {
+            warn(&e.to_string());
+            0
+        }
+    }
+}
+
+/// `string presetString()`
+pub(crate) extern "C" fn preset_string(
+    ck_self: *mut chuck::Object,
+    _args: *mut ::std::os::raw::c_void,
+    return_: *mut chuck::Chuck_DL_Return,
+    vm: *mut chuck::VM,
+    shred: *mut chuck::VM_Shred,
+    api: chuck::CK_DL_API,
+) {
+    let ctx = Context::new(vm, shred, api);
+    let target = Target { ck_self, vm, shred, api };
+
+    let string = target
+        .save(&params(&ctx, ck_self))
+        .and_then(|json| ctx.create_string(&json));
+
+    let string = match string {
+        Ok(string) => string,
+        Err(e) => {
+            warn(&e.to_string());
+            std::ptr::null_mut()
+        }
+    };
+
+    unsafe { (*return_).v_string = string };
+}
+
+/// `int savePreset(string path)`
+pub(crate) extern "C" fn save_preset(
+    ck_self: *mut chuck::Object,
+    args: *mut ::std::os::raw::c_void,
+    return_: *mut chuck::Chuck_DL_Return,
+    vm: *mut chuck::VM,
+    shred: *mut chuck::VM_Shred,
+    api: chuck::CK_DL_API,
+) {
+    let ctx = Context::new(vm, shred, api);
+    let target = Target { ck_self, vm, shred, api };
+
+    let result = match path_arg(args) {
+        Some(path) => target.save(&params(&ctx, ck_self)).and_then(|json| {
+            std::fs::write(&path, json)
+                .map_err(|e| Error::Custom(format!("unable to write '{}': {}", path, e)))
+        }),
+        None => Err(Error::NullPointer("preset path")),
+    };
+
+    unsafe { (*return_).v_int = report(result) };
+}
+
+/// `int loadPreset(string path)`
+pub(crate) extern "C" fn load_preset(
+    ck_self: *mut chuck::Object,
+    args: *mut ::std::os::raw::c_void,
+    return_: *mut chuck::Chuck_DL_Return,
+    vm: *mut chuck::VM,
+    shred: *mut chuck::VM_Shred,
+    api: chuck::CK_DL_API,
+) {
+    let ctx = Context::new(vm, shred, api);
+    let target = Target { ck_self, vm, shred, api };
+
+    let result = match path_arg(args) {
+        Some(path) => std::fs::read_to_string(&path)
+            .map_err(|e| Error::Custom(format!("unable to read '{}': {}", path, e)))
+            .and_then(|json| target.load(&params(&ctx, ck_self), &json)),
+        None => Err(Error::NullPointer("preset path")),
+    };
+
+    unsafe { (*return_).v_int = report(result) };
+}
@@ -1,10 +1,42 @@
+use crate::args::{ArgList, ArgSpec, Mfun};
 use crate::chuck;
+use crate::class::ClassDecl;
+use crate::context::Vm;
+use crate::data_offset::DataOffset;
 use crate::cstring::CString;
-use crate::CKResult;
+use crate::main_thread::{self, Bindle, MainThreadHook};
+use crate::member::{MemberType, MemberVar};
+use crate::method::{CKReturn, ChuginObject, Method, MethodFn, This};
+use crate::overload::Overloads;
+use crate::state::{self, VmInfo};
+use crate::{CKResult, Error};
+use std::any::Any;
+use std::cell::RefCell;
+use std::sync::Arc;
+
+extern "C" {
+    fn chugin_query_vm(query: *mut chuck::DL_Query) -> *mut chuck::VM;
+    fn chugin_query_srate(query: *mut chuck::DL_Query) -> chuck::UInt;
+}
 
 /// Chugin Query wrapper class
 pub struct Query {
     query: *mut chuck::DL_Query,
+    /// classes currently being constructed, innermost last
+    classes: RefCell<Vec<OpenClass>>,
+    /// classes extends_ugen accepts as parents: ChucK's UGen base classes and
+    /// the UGen classes registered so far
+    ugen_classes: RefCell<Vec<String>>,
+}
+
+/// ChucK's base classes for UGens
+const UGEN_BASE_CLASSES: &[&str] = &["UGen", "UGen_Multi", "UGen_Stereo", "UAna"];
+
+/// A class that is being constructed and the member function signatures
+/// added to it so far
+struct OpenClass {
+    name: String,
+    signatures: Vec<String>,
 }
 
 /// Chugin Query wrapper class
@@ -12,31 +44,117 @@ impl Query {
     /// Create new wrapper from ChucK type
     pub fn new(query: *mut chuck::DL_Query) -> CKResult<Query> {
         if !query.is_null() {
-            Ok(Query { query: query })
+            let query = Query {
+                query: query,
+                classes: RefCell::new(Vec::new()),
+                ugen_classes: RefCell::new(UGEN_BASE_CLASSES.iter().map(|c| c.to_string()).collect()),
+            };
+
+            if let Ok(vm) = query.vm() {
+                // anything stored for this address belongs to a VM that has
+                // since been destroyed
+                state::clear(vm.as_ptr() as usize);
+
+                // read by callbacks, which may run without a shred; see
+                // Context::srate and log::print
+                let (chout, cherr) = crate::log::console(query.query);
+                let srate = unsafe { chugin_query_srate(query.query) };
+                state::set_info(vm.as_ptr() as usize, VmInfo { srate, chout, cherr });
+
+                crate::log::set_current_vm(vm);
+            }
+
+            Ok(query)
         } else {
-            Err("invalid query object provided")
+            Err(Error::NullPointer("query"))
+        }
+    }
+
+    /// The VM this chugin is being loaded into
+    pub fn vm(&self) -> CKResult<Vm> {
+        match unsafe { chugin_query_vm(self.query) } {
+            vm if vm.is_null() => Err(Error::NullPointer("vm")),
+            vm => Ok(Vm::new(vm)),
         }
     }
 
+    /// Store state of type S for the VM this chugin is being loaded into
+    /// Callbacks reach it through Vm::state, e.g. `ctx.vm().state::<S>()`
+    pub fn init_state<S: Any + Send + Sync>(&self, state: S) -> CKResult<Arc<S>> {
+        Ok(self.vm()?.set_state(state))
+    }
+
+    /// Start building a new class; see ClassBuilder
+    pub fn class(&self, name: &str) -> ClassDecl<'_> {
+        ClassDecl::new(self, name)
+    }
+
     /// Begin a new class
     pub fn begin_class(&self, name: &str, parent: &str) -> CKResult {
-        let name = CString::new(name)?;
-        let parent = CString::new(parent)?;
+        let class = name.to_string();
+        let name = CString::new("class name", name)?;
+        let parent = CString::new("parent class name", parent)?;
 
         let query = match unsafe { self.query.as_ref() } {
             Some(query) => query,
-            None => return Err("invalid query object"),
+            None => return Err(Error::NullPointer("query")),
         };
 
         let begin_class = match query.begin_class {
             Some(f) => f,
-            None => return Err("invalid query object"),
+            None => return Err(Error::MissingQueryFn("begin_class")),
         };
 
         unsafe {
             begin_class(self.query, name.c_str(), parent.c_str());
         }
 
+        self.classes.borrow_mut().push(OpenClass {
+            name: class,
+            signatures: Vec::new(),
+        });
+
+        Ok(())
+    }
+
+    /// Name of the class that is currently being constructed
+    #[cfg(feature = "serde")]
+    pub(crate) fn current_class(&self) -> Option<String> {
+        self.classes.borrow().last().map(|class| class.name.clone())
+    }
+
+    /// Like begin_class, first checking that parent isn't a UGen class known
+    /// to the query, whose subclasses must be declared with begin_ugen_class
+    pub fn begin_object_class(&self, name: &str, parent: &str) -> CKResult {
+        if self.is_ugen_class(parent) {
+            return Err(Error::UGenParent {
+                class: name.to_string(),
+                parent: parent.to_string(),
+            });
+        }
+
+        self.begin_class(name, parent)
+    }
+
+    fn is_ugen_class(&self, class: &str) -> bool {
+        self.ugen_classes.borrow().iter().any(|c| c == class)
+    }
+
+    /// Like begin_class, first checking that parent is a UGen class
+    /// Only ChucK's UGen base classes and classes previously begun this way
+    /// are known to be UGens, since their types can't be looked up while
+    /// the chugin is being queried
+    pub fn begin_ugen_class(&self, name: &str, parent: &str) -> CKResult {
+        if !self.is_ugen_class(parent) {
+            return Err(Error::NotAUGen {
+                class: name.to_string(),
+                parent: parent.to_string(),
+            });
+        }
+
+        self.begin_class(name, parent)?;
+        self.ugen_classes.borrow_mut().push(name.to_string());
+
         Ok(())
     }
 
@@ -44,12 +162,12 @@ impl Query {
     pub fn add_ctor(&self, ctor: chuck::f_ctor) -> CKResult {
         let query = match unsafe { self.query.as_ref() } {
             Some(query) => query,
-            None => return Err("invalid query object"),
+            None => return Err(Error::NullPointer("query")),
         };
 
         let add_ctor = match query.add_ctor {
             Some(f) => f,
-            None => return Err("invalid query object"),
+            None => return Err(Error::MissingQueryFn("add_ctor")),
         };
 
         unsafe {
@@ -63,12 +181,12 @@ impl Query {
     pub fn add_dtor(&self, dtor: chuck::f_dtor) -> CKResult {
         let query = match unsafe { self.query.as_ref() } {
             Some(query) => query,
-            None => return Err("invalid query object"),
+            None => return Err(Error::NullPointer("query")),
         };
 
         let add_dtor = match query.add_dtor {
             Some(f) => f,
-            None => return Err("invalid query object"),
+            None => return Err(Error::MissingQueryFn("add_dtor")),
         };
 
         unsafe {
@@ -80,17 +198,17 @@ impl Query {
 
     /// Add a member variable for the class that is being constructed
     pub fn add_mvar(&self, type_: &str, name: &str, is_const: bool) -> CKResult<chuck::t_CKUINT> {
-        let type_ = CString::new(type_)?;
-        let name = CString::new(name)?;
+        let type_ = CString::new("member variable type", type_)?;
+        let name = CString::new("member variable name", name)?;
 
         let query = match unsafe { self.query.as_ref() } {
             Some(query) => query,
-            None => return Err("invalid query object"),
+            None => return Err(Error::NullPointer("query")),
         };
 
         let add_mvar = match query.add_mvar {
             Some(f) => f,
-            None => return Err("invalid query object"),
+            None => return Err(Error::MissingQueryFn("add_mvar")),
         };
 
         Ok(unsafe {
@@ -103,6 +221,18 @@ impl Query {
         })
     }
 
+    /// Add the `@data` member variable that holds the Rust object and record
+    /// its offset
+    pub fn add_data_mvar(&self, offset: &DataOffset) -> CKResult {
+        offset.set(self.add_mvar("int", "@data", false)? as usize)
+    }
+
+    /// Add a typed member variable and record its offset
+    pub fn add_typed_mvar<T: MemberType>(&self, var: &MemberVar<T>) -> CKResult {
+        var.offset()
+            .set(self.add_mvar(T::TYPE_NAME, var.name(), var.is_const())? as usize)
+    }
+
     /// Add a tick function for the class that is being constructed
     pub fn add_mfun(
         &self,
@@ -111,22 +241,33 @@ impl Query {
         name: &str,
         args: &[(String, String)],
     ) -> CKResult {
-        let type_ = CString::new(type_)?;
-        let name = CString::new(name)?;
+        self.add_signature(name, args)?;
+
+        // a method returning This returns the class it belongs to
+        let type_ = match type_ {
+            This::TYPE_NAME => match self.classes.borrow().last() {
+                Some(class) => class.name.clone(),
+                None => return Err(Error::Custom(format!("{} returns this outside of a class", name))),
+            },
+            _ => type_.to_string(),
+        };
+
+        let type_ = CString::new("member function return type", &type_)?;
+        let name = CString::new("member function name", name)?;
 
         let query = match unsafe { self.query.as_ref() } {
             Some(query) => query,
-            None => return Err("invalid query object"),
+            None => return Err(Error::NullPointer("query")),
         };
 
         let add_mfun = match query.add_mfun {
             Some(f) => f,
-            None => return Err("invalid query object"),
+            None => return Err(Error::MissingQueryFn("add_mfun")),
         };
 
         let add_arg = match query.add_arg {
             Some(f) => f,
-            None => return Err("invalid query object"),
+            None => return Err(Error::MissingQueryFn("add_arg")),
         };
 
         unsafe {
@@ -134,8 +275,8 @@ impl Query {
         }
 
         for arg in args {
-            let type_ = CString::new(&arg.0)?;
-            let name = CString::new(&arg.1)?;
+            let type_ = CString::new("argument type", &arg.0)?;
+            let name = CString::new("argument name", &arg.1)?;
 
             unsafe {
                 add_arg(self.query, type_.c_str(), name.c_str());
@@ -145,20 +286,144 @@ impl Query {
         Ok(())
     }
 
-    /// Add a tick function for the class that is being constructed
-    pub fn add_ugen_func(&self, tick: chuck::f_tick, num_in: u32, num_out: u32) -> CKResult {
+    /// Record a member function signature for the current class, rejecting
+    /// one that was already added; ChucK overloads ignore the return type
+    fn add_signature(&self, name: &str, args: &[(String, String)]) -> CKResult {
+        let mut classes = self.classes.borrow_mut();
+
+        let class = match classes.last_mut() {
+            Some(class) => class,
+            None => return Ok(()),
+        };
+
+        let types: Vec<&str> = args.iter().map(|arg| arg.0.as_str()).collect();
+        let signature = format!("{}({})", name, types.join(", "));
+
+        if class.signatures.contains(&signature) {
+            return Err(Error::DuplicateSignature {
+                class: class.name.clone(),
+                signature,
+            });
+        }
+
+        class.signatures.push(signature);
+
+        Ok(())
+    }
+
+    /// Add every signature of an overload set under one name
+    pub fn add_overloads(&self, overloads: &Overloads) -> CKResult {
+        for signature in overloads.signatures() {
+            self.add_mfun(signature.mfun, &signature.type_, overloads.name(), &signature.args)?;
+        }
+
+        Ok(())
+    }
+
+    /// Add `float name(float)` and `float name()` from a setter and getter,
+    /// e.g. as generated by mfun_setter_getter_float!
+    pub fn add_setter_getter(
+        &self,
+        name: &str,
+        setter: chuck::f_mfun,
+        getter: chuck::f_mfun,
+    ) -> CKResult {
+        self.add_overloads(&Overloads::new(name).setter_getter(setter, getter))
+    }
+
+    /// Add a member function whose argument types are checked against the
+    /// types it decodes
+    pub fn add_typed_mfun<A: ArgList>(
+        &self,
+        mfun: Mfun<A>,
+        type_: &str,
+        name: &str,
+        args: ArgSpec<A>,
+    ) -> CKResult {
+        self.add_mfun(mfun.get(), type_, name, &args.to_pairs())
+    }
+
+    /// Document the function that was added last
+    pub fn doc_func(&self, doc: &str) -> CKResult {
+        let doc = CString::new("function documentation", doc)?;
+
+        let query = match unsafe { self.query.as_ref() } {
+            Some(query) => query,
+            None => return Err(Error::NullPointer("query")),
+        };
+
+        let doc_func = match query.doc_func {
+            Some(f) => f,
+            None => return Err(Error::MissingQueryFn("doc_func")),
+        };
+
+        match unsafe { doc_func(self.query, doc.c_str()) } {
+            0 => Err(Error::QueryCallFailed("doc_func")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Add a member function implemented by a plain Rust function or
+    /// closure without captures, e.g. `|s: &mut Osc, f: f64| s.set_freq(f)`
+    /// Argument and return types are taken from its signature
+    pub fn add_method<T, A, R, F>(&self, name: &str, f: F) -> CKResult
+    where
+        T: ChuginObject,
+        A: ArgList,
+        R: CKReturn,
+        F: Method<T, A, R>,
+    {
+        let method = MethodFn::new(f);
+        self.add_mfun(method.get(), method.return_type(), name, &method.args())
+    }
+
+    /// Add a tick function and optional pmsg handler for the class that is
+    /// being constructed
+    pub fn add_ugen_func(
+        &self,
+        tick: chuck::f_tick,
+        pmsg: chuck::f_pmsg,
+        num_in: u32,
+        num_out: u32,
+    ) -> CKResult {
         let query = match unsafe { self.query.as_ref() } {
             Some(query) => query,
-            None => return Err("invalid query object"),
+            None => return Err(Error::NullPointer("query")),
         };
 
         let add_ugen_func = match query.add_ugen_func {
             Some(f) => f,
-            None => return Err("invalid query object"),
+            None => return Err(Error::MissingQueryFn("add_ugen_func")),
         };
 
         unsafe {
-            add_ugen_func(self.query, tick, None, num_in.into(), num_out.into());
+            add_ugen_func(self.query, tick, pmsg, num_in.into(), num_out.into());
+        }
+
+        Ok(())
+    }
+
+    /// Add a multi-channel tick function and optional pmsg handler for the
+    /// class that is being constructed
+    pub fn add_ugen_funcf(
+        &self,
+        tickf: chuck::f_tickf,
+        pmsg: chuck::f_pmsg,
+        num_in: u32,
+        num_out: u32,
+    ) -> CKResult {
+        let query = match unsafe { self.query.as_ref() } {
+            Some(query) => query,
+            None => return Err(Error::NullPointer("query")),
+        };
+
+        let add_ugen_funcf = match query.add_ugen_funcf {
+            Some(f) => f,
+            None => return Err(Error::MissingQueryFn("add_ugen_funcf")),
+        };
+
+        unsafe {
+            add_ugen_funcf(self.query, tickf, pmsg, num_in.into(), num_out.into());
         }
 
         Ok(())
@@ -168,17 +433,66 @@ impl Query {
     pub fn end_class(&self) -> CKResult {
         let query = match unsafe { self.query.as_ref() } {
             Some(query) => query,
-            None => return Err("invalid query object"),
+            None => return Err(Error::NullPointer("query")),
         };
 
         let end_class = match query.end_class {
             Some(f) => f,
-            None => return Err("invalid query object"),
+            None => return Err(Error::MissingQueryFn("end_class")),
+        };
+
+        let class = match self.classes.borrow_mut().pop() {
+            Some(class) => class.name,
+            None => return Err(Error::NoCurrentClass),
         };
 
         match unsafe { end_class(self.query) } {
-            0 => Err("failed to end_class"),
+            0 => Err(Error::EndClassFailed { class }),
             _ => Ok(()),
         }
     }
+
+    /// Create a hook that runs on the host's main thread
+    /// `hook` is run when the host activates the hook; `quit` is run when the
+    /// host asks it to stop, after which both closures are dropped
+    /// Keep the returned MainThreadHook for as long as the hook should run:
+    /// dropping it before activate() or after deactivate() drops the
+    /// closures without running quit
+    pub fn main_thread_hook<H, Q>(&self, hook: H, quit: Q) -> CKResult<MainThreadHook>
+    where
+        H: FnMut() + Send + 'static,
+        Q: FnOnce() + Send + 'static,
+    {
+        let query = match unsafe { self.query.as_ref() } {
+            Some(query) => query,
+            None => return Err(Error::NullPointer("query")),
+        };
+
+        let create_main_thread_hook = match query.create_main_thread_hook {
+            Some(f) => f,
+            None => return Err(Error::MissingQueryFn("create_main_thread_hook")),
+        };
+
+        let bindle = Box::into_raw(Box::new(Bindle::new(hook, quit)));
+
+        let hook = unsafe {
+            create_main_thread_hook(
+                self.query,
+                Some(main_thread::hook_trampoline::<H, Q>),
+                Some(main_thread::quit_trampoline::<H, Q>),
+                bindle as *mut ::std::os::raw::c_void,
+            )
+        };
+
+        if hook.is_null() {
+            // ChucK never saw the bindle, so reclaim it here
+            drop(unsafe { Box::from_raw(bindle) });
+        }
+
+        MainThreadHook::new(
+            hook,
+            bindle as *mut ::std::os::raw::c_void,
+            main_thread::drop_bindle::<H, Q>,
+        )
+    }
 }
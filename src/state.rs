@@ -0,0 +1,129 @@
+// State shared by all objects of a chugin within one ChucK instance
+//
+// DL 8.0 doesn't tell chugins when a VM shuts down, so a VM's state lives
+// until the chugin is queried again by a VM at the same address, which
+// means the earlier VM is gone
+//
+// The sample rate and console, which callbacks read on the audio thread, are
+// kept apart in a fixed table of atomics so that reading them never waits on
+// the lock
+
+use crate::chuck;
+use std::any::{Any, TypeId};
+use std::os::raw::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+type Entry = ((usize, TypeId), Arc<dyn Any + Send + Sync>);
+
+/// State of each VM, keyed by the VM's address and the state's type
+static STATES: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+fn states() -> std::sync::MutexGuard<'static, Vec<Entry>> {
+    STATES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Store state for a VM, replacing any previous state of the same type
+pub(crate) fn insert<S: Any + Send + Sync>(vm: usize, state: S) -> Arc<S> {
+    let key = (vm, TypeId::of::<S>());
+    let state = Arc::new(state);
+    let mut states = states();
+
+    match states.iter_mut().find(|(k, _)| *k == key) {
+        Some(entry) => entry.1 = state.clone(),
+        None => states.push((key, state.clone())),
+    }
+
+    state
+}
+
+pub(crate) fn get<S: Any + Send + Sync>(vm: usize) -> Option<Arc<S>> {
+    let key = (vm, TypeId::of::<S>());
+
+    states()
+        .iter()
+        .find(|(k, _)| *k == key)
+        .and_then(|(_, state)| state.clone().downcast::<S>().ok())
+}
+
+pub(crate) fn remove<S: Any + Send + Sync>(vm: usize) -> Option<Arc<S>> {
+    let key = (vm, TypeId::of::<S>());
+    let mut states = states();
+
+    let index = states.iter().position(|(k, _)| *k == key)?;
+    states.swap_remove(index).1.downcast::<S>().ok()
+}
+
+/// Drop all state of a VM; Query::new calls this before the VM's query
+pub(crate) fn clear(vm: usize) {
+    states().retain(|((k, _), _)| *k != vm);
+}
+
+/// What ChucK reported about a VM when it queried the chugin
+#[derive(Clone, Copy)]
+pub(crate) struct VmInfo {
+    pub(crate) srate: chuck::UInt,
+    pub(crate) chout: *mut c_void,
+    pub(crate) cherr: *mut c_void,
+}
+
+struct Slot {
+    vm: AtomicUsize,
+    srate: AtomicU64,
+    chout: AtomicPtr<c_void>,
+    cherr: AtomicPtr<c_void>,
+}
+
+/// Number of VMs whose info is kept; later VMs get none
+const SLOTS: usize = 64;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: Slot = Slot {
+    vm: AtomicUsize::new(0),
+    srate: AtomicU64::new(0),
+    chout: AtomicPtr::new(ptr::null_mut()),
+    cherr: AtomicPtr::new(ptr::null_mut()),
+};
+
+/// Info of each VM, keyed by the VM's address; a slot is claimed by the
+/// first query of an address and reused by later ones
+static INFO: [Slot; SLOTS] = [EMPTY; SLOTS];
+
+/// Record a VM's info; Query::new calls this during the VM's query
+pub(crate) fn set_info(vm: usize, info: VmInfo) {
+    if vm == 0 {
+        return;
+    }
+
+    let slot = INFO.iter().find(|slot| slot.vm.load(Ordering::Acquire) == vm).or_else(|| {
+        INFO.iter().find(|slot| {
+            slot.vm
+                .compare_exchange(0, vm, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        })
+    });
+
+    if let Some(slot) = slot {
+        // chuck::UInt is only 32 bits on Windows
+        #[allow(clippy::useless_conversion)]
+        slot.srate.store(u64::from(info.srate), Ordering::Release);
+        slot.chout.store(info.chout, Ordering::Release);
+        slot.cherr.store(info.cherr, Ordering::Release);
+    }
+}
+
+/// A VM's info, without taking the lock
+pub(crate) fn info(vm: usize) -> Option<VmInfo> {
+    if vm == 0 {
+        return None;
+    }
+
+    let slot = INFO.iter().find(|slot| slot.vm.load(Ordering::Acquire) == vm)?;
+
+    Some(VmInfo {
+        srate: slot.srate.load(Ordering::Acquire) as chuck::UInt,
+        chout: slot.chout.load(Ordering::Acquire),
+        cherr: slot.cherr.load(Ordering::Acquire),
+    })
+}
@@ -0,0 +1,99 @@
+// Rust classes extending other Rust classes
+
+use crate::chuck;
+use crate::class::ClassBuilder;
+use crate::context::Context;
+use crate::data_offset::DataOffset;
+use crate::error::DataError;
+use crate::method::ChuginObject;
+use crate::object::{ChuginClass, ChuginCtor};
+use crate::util;
+use std::any::Any;
+use std::os::raw::c_void;
+
+/// Lets a trait object be downcast to its concrete type; make the trait
+/// subclasses implement extend this, e.g. `trait Filter: chugin::AsAny`
+pub trait AsAny: Any {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Data of a Rust base class, holding the data of whichever Rust subclass
+/// the object is, typically as a `Box<dyn Trait>` field
+/// Methods added for the base operate on this type and dispatch through the
+/// trait object
+pub trait ChuginBase: ChuginClass {
+    /// Borrow the subclass data as T; None if the object is of another
+    /// subclass
+    fn downcast_mut<T: 'static>(&mut self) -> Option<&mut T>;
+}
+
+/// Data of a Rust class extending the Rust base class `Base`
+/// It is stored inside the base's data, so methods added for the subclass
+/// and for the base both work on objects of the subclass
+/// Its ctor, added by subclass(), creates it with ChuginCtor::new
+pub trait ChuginSubclass: ChuginCtor + 'static {
+    type Base: ChuginBase;
+
+    /// Wrap the data in the base's data, e.g. as `Box::new(self)`
+    fn into_base(self) -> Self::Base;
+}
+
+impl<T: ChuginSubclass> ChuginObject for T {
+    fn data_offset() -> &'static DataOffset {
+        T::Base::data_offset()
+    }
+
+    unsafe fn with_data<R>(ck_self: *mut chuck::Object, f: impl FnOnce(&mut Self) -> R) -> Result<R, DataError> {
+        T::Base::with_data(ck_self, |base| base.downcast_mut::<T>().map(f))?.ok_or(DataError::Missing)
+    }
+
+    unsafe fn set_data(ck_self: *mut chuck::Object, value: Self) {
+        T::Base::set_data(ck_self, value.into_base())
+    }
+}
+
+extern "C" fn base_dtor<B: ChuginBase>(
+    ck_self: *mut chuck::Object,
+    _vm: *mut chuck::VM,
+    _shred: *mut chuck::VM_Shred,
+    _api: chuck::CK_DL_API,
+) {
+    // objects of the base class itself have no data
+    let data = unsafe { (*ck_self).data.add(B::data_offset().get()) as *const usize };
+
+    if unsafe { *data } != 0 {
+        drop(unsafe { util::get_object_data::<B>(ck_self, B::data_offset().get()) });
+    }
+}
+
+extern "C" fn subclass_ctor<T: ChuginSubclass>(
+    ck_self: *mut chuck::Object,
+    _args: *mut c_void,
+    vm: *mut chuck::VM,
+    shred: *mut chuck::VM_Shred,
+    api: chuck::CK_DL_API,
+) {
+    let ctx = Context::new(vm, shred, api).with_object(ck_self);
+    unsafe { T::set_data(ck_self, T::new(&ctx)) };
+}
+
+impl<'q, K> ClassBuilder<'q, K> {
+    /// Make this class the Rust base class B: add the `@data` member variable
+    /// and a dtor dropping B
+    /// Subclasses extend it by name and add their data with subclass()
+    pub fn base<B: ChuginBase>(self) -> Self {
+        self.dtor(Some(base_dtor::<B>)).data(B::data_offset())
+    }
+
+    /// Add a ctor storing T in the data of the Rust base class this class
+    /// extends; the base's dtor drops it
+    pub fn subclass<T: ChuginSubclass>(self) -> Self {
+        self.ctor(Some(subclass_ctor::<T>))
+    }
+}
@@ -2,6 +2,9 @@
 
 use crate::chuck;
 use crate::cktype::CKType;
+use crate::error::DataError;
+use std::ffi::CStr;
+use std::os::raw::c_char;
 
 /// Set a data member variable in a ChucK object
 /// Note: the type in obj needs to be manually dropped/dealloced at some point
@@ -19,6 +22,43 @@ pub unsafe fn get_object_data<T>(ck_obj: *const chuck::Object, offset: usize) ->
     Box::from_raw(*ptr as *mut T)
 }
 
+/// Stands in for the data of an object while it is borrowed; never the
+/// address of a Box
+const BORROWED: usize = usize::MAX;
+
+/// Borrow the object data for the duration of `f`; the data is marked as
+/// borrowed while `f` runs, so a nested borrow of the same object fails
+/// instead of aliasing it
+/// The mfun, tick and pmsg macros borrow through this
+pub unsafe fn try_with_object_data<T, R>(
+    ck_obj: *const chuck::Object,
+    offset: usize,
+    f: impl FnOnce(&mut T) -> R,
+) -> Result<R, DataError> {
+    let slot = (*ck_obj).data.add(offset) as *mut usize;
+
+    let obj = match *slot {
+        0 => return Err(DataError::Missing),
+        BORROWED => return Err(DataError::Borrowed),
+        obj => obj as *mut T,
+    };
+
+    *slot = BORROWED;
+    let result = f(&mut *obj);
+    *slot = obj as usize;
+    Ok(result)
+}
+
 pub fn get_next_arg<T: CKType>(args: chuck::Args) -> (chuck::Args, T) {
     T::get_next_arg(args)
 }
+
+/// Borrow a C string passed in from ChucK as a &str
+/// Returns None if the pointer is null or the string is not valid UTF-8
+pub unsafe fn get_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(s).to_str().ok()
+}
@@ -0,0 +1,272 @@
+// Background work for chugin objects, handed to and from the audio thread
+// without locking
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+struct Ring<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    /// next slot to read; only written by the consumer
+    head: AtomicUsize,
+    /// next slot to write; only written by the producer
+    tail: AtomicUsize,
+}
+
+// values only ever move from the producer to the consumer
+unsafe impl<T: Send> Send for Ring<T> {}
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        let tail = *self.tail.get_mut();
+        let mut head = *self.head.get_mut();
+
+        while head != tail {
+            unsafe { self.slots[head % self.slots.len()].get_mut().assume_init_drop() };
+            head = head.wrapping_add(1);
+        }
+    }
+}
+
+/// Sending half of a wait-free single-producer/single-consumer queue
+pub struct Producer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+/// Receiving half of a wait-free single-producer/single-consumer queue
+pub struct Consumer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+/// Create a bounded single-producer/single-consumer queue
+/// Neither end ever blocks or allocates, so either can be used in tick
+pub fn spsc<T: Send>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(capacity > 0, "spsc capacity must be non-zero");
+
+    let slots = (0..capacity)
+        .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+        .collect();
+
+    let ring = Arc::new(Ring {
+        slots,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+
+    (Producer { ring: ring.clone() }, Consumer { ring })
+}
+
+impl<T: Send> Producer<T> {
+    /// Queue a value; gives it back if the queue is full
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        let ring = &*self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+
+        if tail.wrapping_sub(ring.head.load(Ordering::Acquire)) == ring.slots.len() {
+            return Err(value);
+        }
+
+        unsafe { (*ring.slots[tail % ring.slots.len()].get()).write(value) };
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+
+        Ok(())
+    }
+}
+
+impl<T: Send> Consumer<T> {
+    /// Take the oldest queued value, if any
+    pub fn pop(&mut self) -> Option<T> {
+        let ring = &*self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+
+        if head == ring.tail.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let value = unsafe { (*ring.slots[head % ring.slots.len()].get()).assume_init_read() };
+        ring.head.store(head.wrapping_add(1), Ordering::Release);
+
+        Some(value)
+    }
+
+    /// Take every queued value, keeping only the newest
+    pub fn pop_latest(&mut self) -> Option<T> {
+        let mut latest = None;
+        while let Some(value) = self.pop() {
+            latest = Some(value);
+        }
+        latest
+    }
+}
+
+/// A background thread owned by a chugin object
+/// Jobs are submitted from mfuns and results collected in tick, both without
+/// locking; keep the Worker in the object data so it is joined when the dtor
+/// drops the object (which waits for a job that is already running)
+pub struct Worker<J: Send + 'static, R: Send + 'static> {
+    jobs: Producer<J>,
+    results: Consumer<R>,
+    quit: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<J: Send + 'static, R: Send + 'static> Worker<J, R> {
+    /// Spawn a thread that runs `work` on each submitted job
+    /// At most `capacity` jobs and `capacity` results are queued at once
+    pub fn spawn<F>(capacity: usize, mut work: F) -> Worker<J, R>
+    where
+        F: FnMut(J) -> R + Send + 'static,
+    {
+        let (jobs, mut job_rx) = spsc::<J>(capacity);
+        let (mut result_tx, results) = spsc::<R>(capacity);
+        let quit = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let quit = quit.clone();
+
+            thread::spawn(move || {
+                while !quit.load(Ordering::Acquire) {
+                    let job = match job_rx.pop() {
+                        Some(job) => job,
+                        None => {
+                            thread::park();
+                            continue;
+                        }
+                    };
+
+                    let mut result = work(job);
+
+                    // wait for tick to make room
+                    while let Err(r) = result_tx.push(result) {
+                        if quit.load(Ordering::Acquire) {
+                            return;
+                        }
+                        result = r;
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                }
+            })
+        };
+
+        Worker {
+            jobs,
+            results,
+            quit,
+            thread: Some(thread),
+        }
+    }
+
+    /// Hand a job to the worker; gives it back if the job queue is full
+    pub fn submit(&mut self, job: J) -> Result<(), J> {
+        self.jobs.push(job)?;
+
+        if let Some(thread) = &self.thread {
+            thread.thread().unpark();
+        }
+
+        Ok(())
+    }
+
+    /// Take the oldest finished result, if any
+    pub fn poll(&mut self) -> Option<R> {
+        self.results.pop()
+    }
+
+    /// Take every finished result, keeping only the newest
+    pub fn poll_latest(&mut self) -> Option<R> {
+        self.results.pop_latest()
+    }
+}
+
+impl<J: Send + 'static, R: Send + 'static> Drop for Worker<J, R> {
+    fn drop(&mut self) {
+        self.quit.store(true, Ordering::Release);
+
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_and_full() {
+        let (mut tx, mut rx) = spsc(2);
+
+        assert_eq!(rx.pop(), None);
+        assert_eq!(tx.push(1), Ok(()));
+        assert_eq!(tx.push(2), Ok(()));
+        assert_eq!(tx.push(3), Err(3));
+
+        assert_eq!(rx.pop(), Some(1));
+        assert_eq!(tx.push(3), Ok(()));
+        assert_eq!(rx.pop(), Some(2));
+        assert_eq!(rx.pop(), Some(3));
+        assert_eq!(rx.pop(), None);
+    }
+
+    #[test]
+    fn wraps_around_the_slots() {
+        let (mut tx, mut rx) = spsc(3);
+
+        for i in 0..10 {
+            assert_eq!(tx.push(i), Ok(()));
+            assert_eq!(tx.push(i + 100), Ok(()));
+            assert_eq!(rx.pop(), Some(i));
+            assert_eq!(rx.pop(), Some(i + 100));
+        }
+        assert_eq!(rx.pop(), None);
+    }
+
+    #[test]
+    fn wraps_around_the_indices() {
+        let (mut tx, mut rx) = spsc(4);
+        tx.ring.head.store(usize::MAX - 1, Ordering::Relaxed);
+        tx.ring.tail.store(usize::MAX - 1, Ordering::Relaxed);
+
+        for i in 0..4 {
+            assert_eq!(tx.push(i), Ok(()));
+        }
+        assert_eq!(tx.push(4), Err(4));
+
+        for i in 0..4 {
+            assert_eq!(rx.pop(), Some(i));
+        }
+        assert_eq!(rx.pop(), None);
+    }
+
+    #[test]
+    fn pop_latest_keeps_the_newest() {
+        let (mut tx, mut rx) = spsc(4);
+
+        assert_eq!(rx.pop_latest(), None);
+        for i in 0..3 {
+            assert_eq!(tx.push(i), Ok(()));
+        }
+        assert_eq!(rx.pop_latest(), Some(2));
+        assert_eq!(rx.pop(), None);
+    }
+
+    #[test]
+    fn drops_queued_values() {
+        let value = Arc::new(());
+        let (mut tx, mut rx) = spsc(4);
+
+        for _ in 0..3 {
+            assert!(tx.push(value.clone()).is_ok());
+        }
+        drop(rx.pop());
+        assert_eq!(Arc::strong_count(&value), 3);
+
+        drop((tx, rx));
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+}